@@ -1,5 +1,5 @@
 // Vertex shader
-//% include "lib/utils/gen_triangle_vs"
+//% include <std/utils/gen_triangle_vs>
 
 // Fragment shader
 
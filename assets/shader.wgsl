@@ -1,11 +1,13 @@
 // Vertex shader
-//% include "lib/utils/gen_triangle_vs"
+//% include <std/utils/gen_triangle_vs>
 
 // Fragment shader
 
-//% include "lib/sdf"
+//% include <std/sdf>
 //% include "generated/mouse_state"
-//% include "lib/noise/perlin"
+//% include "generated/globals"
+//% include "generated/params"
+//% include <std/noise/perlin>
 
 @group(0) @binding(0)
 var t_diffuse: texture_2d<f32>;
@@ -13,15 +15,14 @@ var t_diffuse: texture_2d<f32>;
 var s_diffuse: sampler;
 
 @group(1) @binding(0)
-var<uniform> time: f32;
-
-struct Mouse {
-    pos: vec2<f32>,
-    state: u32,
-}
+var<uniform> globals: Globals;
 
 @group(2) @binding(0)
-var<uniform> mouse: Mouse;
+var<uniform> params: Params;
+
+fn mouse_uv() -> vec2f {
+    return globals.cursor / globals.resolution.xy;
+}
 
 // SDF example constants
 const OUTSIDE_COLOR: vec3<f32> = vec3<f32>(0., 1., 1.);
@@ -36,7 +37,7 @@ const SUB_LINE_THICKNESS: f32 = 0.0025;
 fn sdf_example(uv: vec2f) -> vec4f {
     let box_left = vec2<f32>(.5 - .15, .5);
     var shapes = array<f32, 3>(
-        disc(uv, mouse.pos, .1),
+        disc(uv, mouse_uv(), .1),
         disc(uv, box_left + vec2<f32>(.3, .1), .1),
         rectangle(uv, box_left + vec2<f32>(.15, 0), vec2<f32>(.15, .1)),
     );
@@ -58,7 +59,7 @@ fn sdf_example(uv: vec2f) -> vec4f {
     let lines = min(major_lines, sub_lines);
 
     var color: vec3<f32>;
-    switch mouse.state {
+    switch mouse_state_tag(globals.mouse_state) {
         case Held: {
             color = f32(dist < 0) * vec3<f32>(1.);
         }
@@ -73,23 +74,27 @@ fn sdf_example(uv: vec2f) -> vec4f {
 }
 
 fn perlin_octaves(uv: vec2f, octaves: i32) -> f32 {
-    let noiseScale = length(mouse.pos) * 10.;
+    let noiseScale = length(mouse_uv()) * 10.;
     var col = 0.;
     for (var i: i32 = 1; i < octaves; i++) {
-        let offset = (uv + (time / 10.) * vec2f(f32(i), f32(i))) * f32(10 * i);
+        let offset = (uv + (globals.time / 10.) * vec2f(f32(i), f32(i))) * f32(10 * i);
         col -= perlinNoise2(offset) / f32(i);
     }
     return col;
 }
 
-const DISTORTION_FACTOR: f32 = 0.0025;
+//% if QUALITY >= 2
+const PERLIN_OCTAVES: i32 = 8;
+//% else
+const PERLIN_OCTAVES: i32 = 5;
+//% endif
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     var uv = vec2f(in.tex_coords.x, in.tex_coords.y);
 
-    uv.x += DISTORTION_FACTOR * perlin_octaves(uv, 5);
-    uv.y += DISTORTION_FACTOR * perlin_octaves(uv, 5);
+    uv.x += params.distortion * perlin_octaves(uv, PERLIN_OCTAVES);
+    uv.y += params.distortion * perlin_octaves(uv, PERLIN_OCTAVES);
 
     return sdf_example(uv);
 }
@@ -1,18 +1,46 @@
 use anyhow::*;
-use fs_extra::copy_items;
-use fs_extra::dir::CopyOptions;
 use std::env;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<()> {
-    // This tells Cargo to rerun this script if something in /assets/ changes.
-    println!("cargo:rerun-if-changed=assets/*");
+    println!("cargo:rerun-if-changed=std");
 
     let out_dir = env::var("OUT_DIR")?;
-    let mut copy_options = CopyOptions::new();
-    copy_options.overwrite = true;
-    let mut paths_to_copy = Vec::new();
-    paths_to_copy.push("assets/");
-    copy_items(&paths_to_copy, out_dir, &copy_options)?;
+    embed_std_lib(Path::new(&out_dir).join("std_lib.rs"))?;
 
     Ok(())
 }
+
+/// Writes the list of files of the WGSL standard library, embedded with `include_str!`.
+fn embed_std_lib(target: PathBuf) -> Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("std");
+    let mut files = Vec::new();
+    collect_wgsl(&root, &mut files)?;
+    files.sort();
+
+    let mut code = String::from("pub static FILES: &[(&str, &str)] = &[\n");
+    for file in files {
+        let name = file
+            .strip_prefix(&root)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        writeln!(code, "    ({name:?}, include_str!({:?})),", file.display())?;
+    }
+    code.push_str("];\n");
+
+    std::fs::write(target, code)?;
+    Ok(())
+}
+
+fn collect_wgsl(folder: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_wgsl(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "wgsl") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
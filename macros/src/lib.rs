@@ -1,29 +1,61 @@
-use std::path::Path;
+use std::path::{Component, Path};
 
 use {
     proc_macro::TokenStream,
-    proc_macro_error::{abort_call_site, proc_macro_error},
-    quote::quote,
-    syn::{parse_macro_input, Expr, ExprLit, ItemEnum, Lit, LitStr},
+    proc_macro_error::{abort, abort_call_site, proc_macro_error},
+    quote::{format_ident, quote},
+    syn::{
+        parse::Parser, parse_macro_input, punctuated::Punctuated, Expr, ExprLit, Field, Fields,
+        Ident, ItemEnum, ItemStruct, Lit, LitStr, Meta, MetaNameValue, Token, Type,
+    },
 };
 
+/// Checks that `lit` is a path relative to the generated folder, e.g. `globals.wgsl`.
+fn generated_file(lit: &LitStr) -> String {
+    let file = lit.value();
+    if !Path::new(&file)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        abort!(
+            lit,
+            "Must be a path relative to the generated folder, e.g. `globals.wgsl`"
+        );
+    }
+    file
+}
+
+/// Registers `WGSL` of `name` as `file`, which the crate must collect as
+/// `crate::generated::GeneratedWgsl` with `inventory`.
+fn register(name: &Ident, file: &str) -> proc_macro2::TokenStream {
+    quote! {
+        ::inventory::submit! {
+            crate::generated::GeneratedWgsl {
+                name: #file,
+                source: #name::WGSL,
+            }
+        }
+    }
+}
+
+/// Exports the variants of an enum as WGSL constants, registered as the generated file given
+/// as argument.
+///
+/// Payloads are packed above the tag, configured with `#[wgsl(bits = .., with = .., name = ..)]`
+/// and read in WGSL with `<enum>_<name>(value)`, values being encoded with `to_wgsl_u32`.
 #[proc_macro_error]
 #[proc_macro_attribute]
 pub fn generate_wgsl_enum(args: TokenStream, item: TokenStream) -> TokenStream {
-    let path = parse_macro_input!(args as Option<LitStr>).map(|lit| lit.value());
-
-    let Some(path) = path.as_ref().map(Path::new) else {
-        abort_call_site!("Must provide a path to generate the enum at");
+    let Some(file) = parse_macro_input!(args as Option<LitStr>) else {
+        abort_call_site!("Must provide a file name to generate the enum as");
     };
+    let file = generated_file(&file);
 
-    if let Some(parent) = path.parent() && !parent.exists() {
-        std::fs::create_dir_all(parent).unwrap_or_else(|err| abort_call_site!("Failed to create intermediate parent directories: {}", err));
-    }
-
-    let parsed_item = parse_macro_input!(item as ItemEnum);
+    let mut parsed_item = parse_macro_input!(item as ItemEnum);
 
     let mut counter = None;
-    let content = parsed_item
+    let mut tags = Vec::new();
+    let mut content = parsed_item
         .variants
         .iter()
         .map(|variant| {
@@ -39,6 +71,7 @@ pub fn generate_wgsl_enum(args: TokenStream, item: TokenStream) -> TokenStream {
             } else {
                 counter = Some(counter.map_or(0, |c| c + 1));
             }
+            tags.push(counter.unwrap());
 
             format!(
                 "const {}: u32 = {};",
@@ -49,10 +82,413 @@ pub fn generate_wgsl_enum(args: TokenStream, item: TokenStream) -> TokenStream {
         .collect::<Vec<_>>()
         .join("\n");
 
-    std::fs::write(path, content).expect("failed to write .wgsl");
+    // Payloads are packed above the bits of the tag, starting over for every variant
+    let tag_bits = (u32::BITS - tags.iter().max().copied().unwrap_or(0u32).leading_zeros()).max(1);
+    let mut accessors = Vec::<(String, u32, u32)>::new();
+    let mut arms = Vec::new();
+    for (variant, tag) in parsed_item.variants.iter_mut().zip(tags) {
+        let mut shift = tag_bits;
+        let mut encoded = vec![quote! { #tag }];
+        let bindings = (0..variant.fields.len())
+            .map(|i| format_ident!("_{}", i))
+            .collect::<Vec<_>>();
 
+        for (field, binding) in variant.fields.iter_mut().zip(bindings.iter()) {
+            let payload = Payload::take(field);
+            if shift + payload.bits > u32::BITS {
+                abort!(field, "Payloads of `{}` don't fit in a u32", variant.ident);
+            }
+
+            match accessors.iter().find(|(name, ..)| *name == payload.name) {
+                Some(&(_, other_shift, other_bits))
+                    if (other_shift, other_bits) != (shift, payload.bits) =>
+                {
+                    abort!(
+                        field,
+                        "Payload `{}` must have the same bits in every variant",
+                        payload.name
+                    )
+                }
+                Some(_) => {}
+                None => accessors.push((payload.name.clone(), shift, payload.bits)),
+            }
+
+            let value = match &payload.with {
+                Some(with) => quote! { #with(#binding) },
+                None => quote! { (*#binding as u32) },
+            };
+            let mask = payload.mask();
+            encoded.push(quote! { ((#value & #mask) << #shift) });
+            shift += payload.bits;
+        }
+
+        let ident = &variant.ident;
+        let pattern = match &variant.fields {
+            Fields::Unit => quote! { Self::#ident },
+            Fields::Unnamed(_) => quote! { Self::#ident(#(#bindings),*) },
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|field| &field.ident);
+                quote! { Self::#ident { #(#names: #bindings),* } }
+            }
+        };
+        arms.push(quote! { #pattern => #(#encoded)|* });
+    }
+
+    // Enums without payloads are compared directly
+    if !accessors.is_empty() {
+        let prefix = snake_case(&parsed_item.ident.to_string());
+        content += &format!(
+            "\n\nfn {prefix}_tag(value: u32) -> u32 {{\n    return value & {}u;\n}}",
+            u32::MAX >> (u32::BITS - tag_bits)
+        );
+        for (name, shift, bits) in accessors {
+            content += &format!(
+                "\n\nfn {prefix}_{name}(value: u32) -> u32 {{\n    return (value >> {shift}u) & {}u;\n}}",
+                u32::MAX >> (u32::BITS - bits)
+            );
+        }
+    }
+
+    let name = &parsed_item.ident;
+    let registration = register(name, &file);
+    let (impl_generics, ty_generics, where_clause) = parsed_item.generics.split_for_impl();
     quote! {
         #parsed_item
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Constants and accessors of the variants in WGSL, as generated by
+            /// `generate_wgsl_enum`
+            pub const WGSL: &'static str = #content;
+
+            /// Value of the variant in WGSL, its tag in the low bits followed by its payload
+            pub fn to_wgsl_u32(&self) -> u32 {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+
+        #registration
+    }
+    .into()
+}
+
+/// Field of a variant of a `generate_wgsl_enum`, configured with `#[wgsl(...)]`.
+struct Payload {
+    /// Name of the WGSL accessor, the field name by default
+    name: String,
+    bits: u32,
+    /// Function converting a reference to the field to a `u32`, `as u32` by default
+    with: Option<syn::Path>,
+}
+
+impl Payload {
+    /// Reads the `#[wgsl(bits = .., with = .., name = ..)]` attribute of `field`, removing it.
+    fn take(field: &mut Field) -> Self {
+        let mut bits = None;
+        let mut with = None;
+        let mut name = field.ident.as_ref().map(ToString::to_string);
+
+        let attrs = std::mem::take(&mut field.attrs);
+        for attr in attrs {
+            if !attr.path().is_ident("wgsl") {
+                field.attrs.push(attr);
+                continue;
+            }
+
+            let args = attr
+                .parse_args_with(Punctuated::<MetaNameValue, Token![,]>::parse_terminated)
+                .unwrap_or_else(|err| abort!(attr, "{}", err));
+            for arg in args {
+                match (
+                    arg.path.get_ident().map(ToString::to_string).as_deref(),
+                    &arg.value,
+                ) {
+                    (
+                        Some("bits"),
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Int(lit), ..
+                        }),
+                    ) => {
+                        bits = Some(
+                            lit.base10_parse()
+                                .unwrap_or_else(|err| abort!(lit, "{}", err)),
+                        );
+                    }
+                    (Some("with"), Expr::Path(path)) => with = Some(path.path.clone()),
+                    (Some("name"), Expr::Path(path)) if let Some(ident) = path.path.get_ident() => {
+                        name = Some(ident.to_string());
+                    }
+                    _ => abort!(
+                        arg,
+                        "Expected `bits = <int>`, `with = <function>` or `name = <ident>`"
+                    ),
+                }
+            }
+        }
+
+        let Some(name) = name else {
+            abort!(
+                field,
+                "Tuple payloads need a name, set with `#[wgsl(name = ...)]`"
+            );
+        };
+        let bits = bits.or_else(|| match &field.ty {
+            Type::Path(path) => match path.path.get_ident()?.to_string().as_str() {
+                "bool" => Some(1),
+                "u8" => Some(8),
+                "u16" => Some(16),
+                "u32" => Some(32),
+                _ => None,
+            },
+            _ => None,
+        });
+        let Some(bits) = bits.filter(|bits| (1..=u32::BITS).contains(bits)) else {
+            abort!(
+                field,
+                "Payload needs a size between 1 and 32, set with `#[wgsl(bits = ...)]`"
+            );
+        };
+
+        Self { name, bits, with }
+    }
+
+    fn mask(&self) -> u32 {
+        u32::MAX >> (u32::BITS - self.bits)
+    }
+}
+
+/// `MouseState` to `mouse_state`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Layout of a type in WGSL.
+struct WgslType {
+    name: String,
+    align: usize,
+    size: usize,
+}
+
+impl WgslType {
+    fn new(name: impl ToString, align: usize, size: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            align,
+            size,
+        }
+    }
+
+    /// Scalars, and glam's vectors and matrices whose layout matches the WGSL one.
+    fn from_path(name: &str) -> Option<Self> {
+        let (suffix, dimensions) = match name {
+            "f32" | "u32" | "i32" => return Some(Self::new(name, 4, 4)),
+            "Vec2" | "Vec3" | "Vec4" => ("f", &name[3..]),
+            "UVec2" | "UVec3" | "UVec4" | "IVec2" | "IVec3" | "IVec4" => (&name[..1], &name[4..]),
+            "Mat2" => return Some(Self::new("mat2x2f", 8, 16)),
+            "Mat4" => return Some(Self::new("mat4x4f", 16, 64)),
+            _ => return None,
+        };
+        Self::vector(&suffix.to_lowercase(), dimensions.parse().ok()?)
+    }
+
+    fn vector(suffix: &str, dimensions: usize) -> Option<Self> {
+        let (align, size) = match dimensions {
+            2 => (8, 8),
+            3 => (16, 12),
+            4 => (16, 16),
+            _ => return None,
+        };
+        Some(Self::new(format!("vec{dimensions}{suffix}"), align, size))
+    }
+
+    /// Maps `ty` to its WGSL equivalent, arrays of 2 to 4 scalars being vectors.
+    ///
+    /// Aborts on types without one, or whose stride differs between Rust and WGSL.
+    fn from_type(ty: &Type, uniform: bool) -> Self {
+        match ty {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .and_then(|segment| Self::from_path(&segment.ident.to_string()))
+                .unwrap_or_else(|| abort!(ty, "Type has no WGSL equivalent")),
+            Type::Array(array) => {
+                let Expr::Lit(ExprLit {
+                    lit: Lit::Int(len), ..
+                }) = &array.len
+                else {
+                    abort!(array.len, "Array length must be a literal");
+                };
+                let len = len
+                    .base10_parse::<usize>()
+                    .unwrap_or_else(|err| abort!(len, "{}", err));
+
+                let element = Self::from_type(&array.elem, uniform);
+                if ["f32", "u32", "i32"].contains(&element.name.as_str())
+                    && let Some(vector) = Self::vector(&element.name[..1], len)
+                {
+                    return vector;
+                }
+
+                // Uniform arrays have their stride rounded up to 16, which Rust can't pad
+                let align = match uniform {
+                    true => element.align.next_multiple_of(16),
+                    false => element.align,
+                };
+                if element.size.next_multiple_of(align) != element.size {
+                    abort!(
+                        array.elem,
+                        "Elements of `array<{}>` are {} bytes apart in WGSL but {} in Rust{}",
+                        element.name,
+                        element.size.next_multiple_of(align),
+                        element.size,
+                        match uniform {
+                            true => ", use 4 component vectors in uniforms",
+                            false => "",
+                        }
+                    );
+                }
+                Self::new(
+                    format!("array<{}, {len}>", element.name),
+                    align,
+                    element.size * len,
+                )
+            }
+            _ => abort!(ty, "Type has no WGSL equivalent"),
+        }
+    }
+}
+
+/// `///` comments of `attrs`, one line each.
+fn doc_lines(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Makes a `#[repr(C)]` struct match the layout of a WGSL struct, registered as the generated
+/// file given as argument.
+///
+/// Padding fields are inserted where WGSL aligns fields further than Rust, the layout is
+/// asserted at compile time and the WGSL source is available as `WGSL`. Layout follows the
+/// rules of the uniform address space, unless `storage` is given after the file name.
+///
+/// Must be placed above derives, so that they see the padding.
+#[proc_macro_error]
+#[proc_macro_attribute]
+pub fn generate_wgsl_struct(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated
+        .parse(args)
+        .unwrap_or_else(|err| abort_call_site!("{}", err));
+    let mut args = args.iter();
+    let Some(Expr::Lit(ExprLit {
+        lit: Lit::Str(file),
+        ..
+    })) = args.next()
+    else {
+        abort_call_site!("Must provide a file name to generate the struct as");
+    };
+    let file = generated_file(file);
+    let uniform = match args.next() {
+        None => true,
+        Some(Expr::Path(arg)) if arg.path.is_ident("storage") => false,
+        Some(arg) => abort!(arg, "Expected `storage`"),
+    };
+
+    let mut parsed_item = parse_macro_input!(item as ItemStruct);
+    let name = &parsed_item.ident;
+    if !parsed_item.generics.params.is_empty() {
+        abort!(parsed_item.generics, "Generic structs are not supported");
+    }
+    let Fields::Named(fields) = &parsed_item.fields else {
+        abort!(parsed_item, "Only structs with named fields are supported");
+    };
+
+    let mut wgsl = format!("// Generated from `{name}`, edit the Rust struct instead\n");
+    for line in doc_lines(&parsed_item.attrs) {
+        wgsl += format!("// {line}").trim_end();
+        wgsl += "\n";
+    }
+    wgsl += &format!("struct {name} {{\n");
+
+    let mut padded_fields = Punctuated::<Field, Token![,]>::new();
+    let mut assertions = Vec::new();
+    let mut end = 0usize;
+    let mut struct_align = 1;
+    let padding = |padded_fields: &mut Punctuated<Field, Token![,]>, size: usize| {
+        let ident = format_ident!("_padding{}", padded_fields.len());
+        padded_fields.push(syn::parse_quote! { #ident: [u8; #size] });
+    };
+
+    for field in fields.named.iter() {
+        let ident = field.ident.as_ref().expect("Fields should be named");
+        let ty = WgslType::from_type(&field.ty, uniform);
+        let offset = end.next_multiple_of(ty.align);
+        if offset > end {
+            padding(&mut padded_fields, offset - end);
+        }
+        padded_fields.push(field.clone());
+        end = offset + ty.size;
+        struct_align = struct_align.max(ty.align);
+
+        for line in doc_lines(&field.attrs) {
+            wgsl += format!("    // {line}").trim_end();
+            wgsl += "\n";
+        }
+        wgsl += &format!("    {ident}: {},\n", ty.name);
+
+        let message = format!(
+            "`{name}::{ident}` should be at offset {offset} as in WGSL, is `{name}` #[repr(C)]?"
+        );
+        assertions.push(quote! {
+            const _: () = assert!(::std::mem::offset_of!(#name, #ident) == #offset, #message);
+        });
+    }
+    wgsl += "}\n";
+
+    // Structs are as large as a multiple of their alignment
+    let size = end.next_multiple_of(struct_align);
+    if size > end {
+        padding(&mut padded_fields, size - end);
+    }
+    let message = format!("`{name}` should be {size} bytes large as in WGSL");
+    assertions.push(quote! {
+        const _: () = assert!(::std::mem::size_of::<#name>() == #size, #message);
+    });
+
+    if let Fields::Named(fields) = &mut parsed_item.fields {
+        fields.named = padded_fields;
+    }
+
+    let registration = register(name, &file);
+    quote! {
+        #parsed_item
+
+        impl #name {
+            /// Definition of the struct in WGSL, as generated by `generate_wgsl_struct`
+            pub const WGSL: &'static str = #wgsl;
+        }
+
+        #(#assertions)*
+
+        #registration
     }
     .into()
 }
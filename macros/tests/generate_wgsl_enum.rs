@@ -1,6 +1,22 @@
 use indoc::indoc;
 use macros::generate_wgsl_enum;
 
+/// Registry of the crate using the macros
+mod generated {
+    pub struct GeneratedWgsl {
+        pub name: &'static str,
+        pub source: &'static str,
+    }
+
+    inventory::collect!(GeneratedWgsl);
+
+    pub fn source(name: &str) -> Option<&'static str> {
+        inventory::iter::<GeneratedWgsl>()
+            .find(|file| file.name == name)
+            .map(|file| file.source)
+    }
+}
+
 #[test]
 fn generate_wgsl_enum() {
     #[allow(unused)]
@@ -14,7 +30,7 @@ fn generate_wgsl_enum() {
     }
 
     let _ = MyEnum::First;
-    let file_content = std::fs::read_to_string("test.wgsl").expect("file should have been created");
+    let file_content = generated::source("test.wgsl").expect("file should have been registered");
     let expected_content = indoc! {"
         const First: u32 = 0;
         const Second: u32 = 1;
@@ -23,4 +39,66 @@ fn generate_wgsl_enum() {
     "};
 
     assert_eq!(file_content, expected_content);
+    assert_eq!(MyEnum::WGSL, expected_content);
+}
+
+#[test]
+fn generate_wgsl_enum_payloads() {
+    fn side_count(corners: &[(f32, f32)]) -> u32 {
+        corners.len() as u32
+    }
+
+    #[allow(unused)]
+    #[generate_wgsl_enum("test_payloads.wgsl")]
+    enum Shape {
+        Empty,
+        Circle {
+            filled: bool,
+            #[wgsl(bits = 4)]
+            radius: u8,
+        },
+        Square(#[wgsl(name = filled)] bool),
+        Polygon(#[wgsl(name = sides, bits = 6, with = side_count)] Vec<(f32, f32)>),
+    }
+
+    let file_content =
+        generated::source("test_payloads.wgsl").expect("file should have been registered");
+    let expected_content = indoc! {"
+        const Empty: u32 = 0;
+        const Circle: u32 = 1;
+        const Square: u32 = 2;
+        const Polygon: u32 = 3;
+
+        fn shape_tag(value: u32) -> u32 {
+            return value & 3u;
+        }
+
+        fn shape_filled(value: u32) -> u32 {
+            return (value >> 2u) & 1u;
+        }
+
+        fn shape_radius(value: u32) -> u32 {
+            return (value >> 3u) & 15u;
+        }
+
+        fn shape_sides(value: u32) -> u32 {
+            return (value >> 2u) & 63u;
+        }\
+    "};
+    assert_eq!(file_content, expected_content);
+
+    assert_eq!(Shape::Empty.to_wgsl_u32(), 0);
+    let circle = Shape::Circle {
+        filled: true,
+        radius: 5,
+    };
+    assert_eq!(circle.to_wgsl_u32(), 1 | 1 << 2 | 5 << 3);
+    // Payloads are truncated to their bits
+    let circle = Shape::Circle {
+        filled: false,
+        radius: 20,
+    };
+    assert_eq!(circle.to_wgsl_u32(), 1 | 4 << 3);
+    assert_eq!(Shape::Square(true).to_wgsl_u32(), 2 | 1 << 2);
+    assert_eq!(Shape::Polygon(vec![(0., 0.); 5]).to_wgsl_u32(), 3 | 5 << 2);
 }
@@ -0,0 +1,72 @@
+use indoc::indoc;
+use macros::generate_wgsl_struct;
+
+/// Registry of the crate using the macros
+mod generated {
+    pub struct GeneratedWgsl {
+        pub name: &'static str,
+        pub source: &'static str,
+    }
+
+    inventory::collect!(GeneratedWgsl);
+
+    pub fn source(name: &str) -> Option<&'static str> {
+        inventory::iter::<GeneratedWgsl>()
+            .find(|file| file.name == name)
+            .map(|file| file.source)
+    }
+}
+
+#[test]
+fn generate_wgsl_struct() {
+    /// Uniform of the test
+    #[allow(unused)]
+    #[generate_wgsl_struct("test_struct.wgsl")]
+    #[derive(Debug, Default)]
+    #[repr(C)]
+    struct MyUniform {
+        /// Position of the center
+        position: [f32; 3],
+        scale: f32,
+        offset: [f32; 2],
+        color: [f32; 4],
+        corners: [[f32; 4]; 2],
+        count: u32,
+    }
+
+    #[allow(unused)]
+    #[generate_wgsl_struct("test_storage.wgsl", storage)]
+    #[repr(C)]
+    struct MyStorage {
+        weights: [f32; 5],
+        origin: glam::Vec3,
+    }
+
+    let file_content =
+        generated::source("test_struct.wgsl").expect("file should have been registered");
+    let expected_content = indoc! {"
+        // Generated from `MyUniform`, edit the Rust struct instead
+        // Uniform of the test
+        struct MyUniform {
+            // Position of the center
+            position: vec3f,
+            scale: f32,
+            offset: vec2f,
+            color: vec4f,
+            corners: array<vec4f, 2>,
+            count: u32,
+        }
+    "};
+    assert_eq!(file_content, expected_content);
+    assert_eq!(MyUniform::WGSL, expected_content);
+
+    // Padded after `offset` and at the end
+    assert_eq!(std::mem::offset_of!(MyUniform, color), 32);
+    assert_eq!(std::mem::size_of::<MyUniform>(), 96);
+    let _ = MyUniform::default();
+
+    // Arrays of scalars are only allowed in storage buffers
+    assert!(MyStorage::WGSL.contains("    weights: array<f32, 5>,\n    origin: vec3f,\n"));
+    assert_eq!(std::mem::offset_of!(MyStorage, origin), 32);
+    assert_eq!(std::mem::size_of::<MyStorage>(), 48);
+}
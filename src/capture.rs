@@ -0,0 +1,242 @@
+use crate::utils::civil_from_days;
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+
+/// A texture being read back, along with what it was rendered with.
+pub struct Capture {
+    pub readback: Readback,
+    /// Offscreen copy of the final image, kept alive until the copy is submitted
+    pub texture: Option<wgpu::Texture>,
+    /// PNG text metadata, e.g. the shader and time of the frame
+    pub metadata: Vec<(&'static str, String)>,
+}
+
+impl Capture {
+    /// Waits for the readback to be done and writes it as a PNG, once submitted.
+    pub fn save(&self, device: &wgpu::Device, path: &Path) -> anyhow::Result<()> {
+        let image = self.readback.read(device)?;
+        save_png(&image, path, &self.metadata)
+    }
+}
+
+/// Copies a texture into a mappable buffer, to read it back as an RGBA image.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    /// Rows of a texture copy must be aligned, the padding gets stripped when reading back
+    padded_bytes_per_row: u32,
+}
+
+impl Readback {
+    /// Fails if `texture` can't be copied from, or has a format that can't be converted to
+    /// 8-bit RGBA.
+    pub fn new(device: &wgpu::Device, texture: &wgpu::Texture) -> anyhow::Result<Self> {
+        let format = texture.format();
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            bail!("Texture can't be copied from");
+        }
+        if !Self::is_supported(format) {
+            bail!("Can't read back textures of format {format:?}");
+        }
+
+        let size = texture.size();
+        let bytes_per_row = size.width * format.block_copy_size(None).unwrap_or(0);
+        let padded_bytes_per_row =
+            bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: padded_bytes_per_row as u64 * size.height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            buffer,
+            format,
+            size,
+            padded_bytes_per_row,
+        })
+    }
+
+    pub fn is_supported(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+                | wgpu::TextureFormat::Rgba16Float
+        )
+    }
+
+    /// Records the copy of `texture`, which must be the one this readback was created for.
+    pub fn copy(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.size.height),
+                },
+            },
+            self.size,
+        );
+    }
+
+    /// Waits for the copy to be done and converts the texture to sRGB-encoded RGBA.
+    pub fn read(&self, device: &wgpu::Device) -> anyhow::Result<image::RgbaImage> {
+        let slice = self.buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            // `read` only stops listening once it got the result or gave up
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        rx.recv()?.context("Failed to map readback buffer")?;
+
+        let bytes_per_row = self.size.width * self.format.block_copy_size(None).unwrap_or(0);
+        let mapped = slice.get_mapped_range();
+        let pixels = to_rgba8(
+            self.format,
+            &mapped,
+            bytes_per_row as usize,
+            self.padded_bytes_per_row as usize,
+        );
+        drop(mapped);
+        self.buffer.unmap();
+
+        Ok(
+            image::RgbaImage::from_raw(self.size.width, self.size.height, pixels)
+                .expect("Readback should match the size of the texture"),
+        )
+    }
+}
+
+/// Strips the padding of the rows of `bytes` and converts its pixels of `format` to
+/// sRGB-encoded RGBA.
+fn to_rgba8(
+    format: wgpu::TextureFormat,
+    bytes: &[u8],
+    bytes_per_row: usize,
+    padded_bytes_per_row: usize,
+) -> Vec<u8> {
+    let rows = bytes
+        .chunks(padded_bytes_per_row)
+        .map(|row| &row[..bytes_per_row]);
+    match format {
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => rows
+            .flat_map(|row| row.chunks_exact(4))
+            .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+            .collect(),
+        // Float targets hold linear colors, which get encoded when displayed
+        wgpu::TextureFormat::Rgba16Float => rows
+            .flat_map(|row| row.chunks_exact(8))
+            .flat_map(|rgba| {
+                let channel =
+                    |i: usize| half::f16::from_le_bytes([rgba[2 * i], rgba[2 * i + 1]]).to_f32();
+                [
+                    linear_to_srgb(channel(0)),
+                    linear_to_srgb(channel(1)),
+                    linear_to_srgb(channel(2)),
+                    (channel(3).clamp(0., 1.) * 255.).round() as u8,
+                ]
+            })
+            .collect(),
+        _ => rows.flatten().copied().collect(),
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0., 1.);
+    let encoded = match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1. / 2.4) - 0.055,
+    };
+    (encoded * 255.).round() as u8
+}
+
+/// Writes `image` as a PNG, with `text` as `tEXt` chunks.
+pub fn save_png(
+    image: &image::RgbaImage,
+    path: &Path,
+    text: &[(&str, String)],
+) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create '{}'", path.display()))?;
+
+    let mut encoder =
+        png::Encoder::new(std::io::BufWriter::new(file), image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, text) in text {
+        encoder.add_text_chunk(keyword.to_string(), text.to_owned())?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Current UTC date and time, as `2024-06-30_18-42-07.125` to be used in file names.
+pub fn timestamp() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = now.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let time = seconds % 86400;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}.{:03}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        now.subsec_millis(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    #[test]
+    fn readback_conversion() {
+        run_test(Test::new(
+            || {},
+            || {
+                // Two pixels per row, padded to 12 bytes
+                let bgra = [
+                    [10, 20, 30, 255, 1, 2, 3, 4, 0, 0, 0, 0],
+                    [40, 50, 60, 128, 5, 6, 7, 8, 0, 0, 0, 0],
+                ]
+                .concat();
+                assert_eq!(
+                    to_rgba8(wgpu::TextureFormat::Bgra8UnormSrgb, &bgra, 8, 12),
+                    [30, 20, 10, 255, 3, 2, 1, 4, 60, 50, 40, 128, 7, 6, 5, 8]
+                );
+                assert_eq!(
+                    to_rgba8(wgpu::TextureFormat::Rgba8Unorm, &bgra, 8, 12),
+                    [10, 20, 30, 255, 1, 2, 3, 4, 40, 50, 60, 128, 5, 6, 7, 8]
+                );
+
+                // Linear values get encoded, out of range ones clamped, alpha stays linear
+                let rgba16 = [0., 0.5, 1., 0.5, 2., -1., 0.002, 1.]
+                    .map(|value: f32| half::f16::from_f32(value).to_le_bytes())
+                    .concat();
+                assert_eq!(
+                    to_rgba8(wgpu::TextureFormat::Rgba16Float, &rgba16, 16, 16),
+                    [0, 188, 255, 128, 255, 0, 7, 255]
+                );
+            },
+            || {},
+        ));
+    }
+}
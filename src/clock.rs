@@ -0,0 +1,126 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Source of the time seen by shaders and timers, so that it can be controlled when rendering
+/// offline or testing.
+pub trait Clock {
+    /// Time elapsed since the clock started.
+    fn now(&self) -> Duration;
+
+    /// Called once at the end of every frame.
+    fn tick(&self) {}
+}
+
+/// Clock shared by everything that measures time, so that it stays consistent across them.
+pub type SharedClock = Rc<dyn Clock>;
+
+/// Wall clock time.
+pub struct RealClock {
+    start: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Advances by exactly `1 / fps` seconds every frame, regardless of how long frames take.
+pub struct FixedStepClock {
+    fps: f64,
+    frame: Cell<u64>,
+}
+
+impl FixedStepClock {
+    pub fn new(fps: f64) -> Self {
+        Self {
+            fps,
+            frame: Cell::new(0),
+        }
+    }
+
+    /// Index of the current frame, starting at 0.
+    pub fn frame(&self) -> u64 {
+        self.frame.get()
+    }
+}
+
+impl Clock for FixedStepClock {
+    fn now(&self) -> Duration {
+        // Computed from the frame index rather than accumulated, to not drift
+        Duration::from_secs_f64(self.frame.get() as f64 / self.fps)
+    }
+
+    fn tick(&self) {
+        self.frame.set(self.frame.get() + 1);
+    }
+}
+
+/// Only moves when told to.
+#[derive(Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+impl ManualClock {
+    pub fn new(now: Duration) -> Self {
+        Self {
+            now: Cell::new(now),
+        }
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    #[test]
+    fn fixed_step_clock() {
+        run_test(Test::new(
+            || {},
+            || {
+                let clock = FixedStepClock::new(60.);
+                assert_eq!(clock.now(), Duration::ZERO);
+
+                for _ in 0..60 {
+                    clock.tick();
+                }
+                assert_eq!(clock.frame(), 60);
+                assert_eq!(clock.now(), Duration::from_secs(1));
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,369 @@
+use crate::{
+    reflection::BindingError,
+    shader_graph::{FinishedShader, ShaderError, ShaderErrorVariant},
+};
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        })
+    }
+}
+
+/// Points at a file, or at a span of one of its lines.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub path: PathBuf,
+    /// 1-based line and column (in bytes), if the label points inside of the file
+    pub position: Option<(u32, u32)>,
+    /// Length of the span in bytes, starting at `position`
+    pub length: u32,
+    /// Text of the line the label points at, to be displayed under the location
+    pub source_line: Option<String>,
+    pub message: Option<String>,
+}
+
+impl Label {
+    pub fn file(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            position: None,
+            length: 0,
+            source_line: None,
+            message: None,
+        }
+    }
+
+    /// Points at `span`, which must be a slice of `source_line`.
+    pub fn span(path: &Path, line: u32, source_line: &str, span: &str) -> Self {
+        let offset = (span.as_ptr() as usize).saturating_sub(source_line.as_ptr() as usize);
+
+        Self {
+            path: path.to_path_buf(),
+            position: Some((line, offset as u32 + 1)),
+            length: span.len() as u32,
+            source_line: Some(source_line.trim_end().to_owned()),
+            message: None,
+        }
+    }
+
+    /// Maps a byte span of a finished shader back to the file it was included from.
+    fn from_finished(shader: &FinishedShader, span: naga::Span) -> Option<Self> {
+        let range = span.to_range()?;
+        let location = span.location(&shader.code);
+        let origin = shader.source_map.lookup(location.line_number)?;
+
+        let source_line = shader
+            .code
+            .lines()
+            .nth(location.line_number as usize - 1)
+            .unwrap_or_default();
+        // Spans over several lines are only underlined up to the end of the first one
+        let length = range
+            .len()
+            .min((source_line.len() + 1).saturating_sub(location.line_position as usize))
+            as u32;
+
+        Some(Self {
+            path: origin.path.to_path_buf(),
+            position: Some((origin.line, location.line_position)),
+            length,
+            source_line: Some(source_line.to_owned()),
+            message: None,
+        })
+    }
+
+    pub fn with_message(mut self, message: impl ToString) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+}
+
+/// An error or warning about a shader, pointing at the original files it comes from.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Locations related to the diagnostic, the first one being the primary one
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl ToString) -> Self {
+        Self {
+            severity,
+            message: message.to_string(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl ToString) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl ToString) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn from_parse_error(err: &naga::front::wgsl::ParseError, shader: &FinishedShader) -> Self {
+        let labels = err.labels().filter_map(|(span, message)| {
+            Label::from_finished(shader, span).map(|label| label.with_message(message))
+        });
+
+        Self {
+            labels: labels.collect(),
+            ..Self::error(err.message())
+        }
+    }
+
+    pub fn from_validation_error(
+        err: &naga::WithSpan<naga::valid::ValidationError>,
+        shader: &FinishedShader,
+    ) -> Self {
+        let labels = err.spans().filter_map(|(span, message)| {
+            Label::from_finished(shader, *span).map(|label| label.with_message(message))
+        });
+
+        // The actual cause is usually at the bottom of the chain
+        let mut notes = Vec::new();
+        let mut source = std::error::Error::source(err.as_inner());
+        while let Some(err) = source {
+            notes.push(err.to_string());
+            source = err.source();
+        }
+
+        Self {
+            labels: labels.collect(),
+            notes,
+            ..Self::error(err.as_inner())
+        }
+    }
+
+    pub fn from_binding_error(err: &BindingError, shader: &FinishedShader) -> Self {
+        Self {
+            labels: Label::from_finished(shader, err.span).into_iter().collect(),
+            notes: err.note.iter().cloned().collect(),
+            ..Self::error(&err.message)
+        }
+    }
+
+    pub fn from_compilation_message(
+        message: &wgpu::CompilationMessage,
+        shader: &FinishedShader,
+    ) -> Self {
+        let severity = match message.message_type {
+            wgpu::CompilationMessageType::Error => Severity::Error,
+            wgpu::CompilationMessageType::Warning => Severity::Warning,
+            wgpu::CompilationMessageType::Info => Severity::Info,
+        };
+
+        // The message usually embeds its own rendering of the concatenated code, keep the summary
+        let summary = message
+            .message
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default();
+
+        let label = message.location.and_then(|location| {
+            let span = naga::Span::new(location.offset, location.offset + location.length);
+            Label::from_finished(shader, span)
+        });
+
+        Self {
+            labels: label.into_iter().collect(),
+            ..Self::new(severity, summary)
+        }
+    }
+
+    /// Points at the position of a RON error in `source`, the content of the file at `path`.
+    pub fn from_ron_error(err: &ron::error::SpannedError, path: &Path, source: &str) -> Self {
+        let line = err.position.line as u32;
+        let label = match source.lines().nth(err.position.line.saturating_sub(1)) {
+            Some(source_line) => {
+                // Columns of ron count bytes like those of labels, but may be past the line
+                let mut offset = err.position.col.saturating_sub(1).min(source_line.len());
+                while !source_line.is_char_boundary(offset) {
+                    offset -= 1;
+                }
+                Label {
+                    position: Some((line, offset as u32 + 1)),
+                    length: 1,
+                    source_line: Some(source_line.trim_end().to_owned()),
+                    ..Label::file(path)
+                }
+            }
+            None => Label::file(path),
+        };
+
+        Self {
+            labels: vec![label],
+            ..Self::error(&err.code)
+        }
+    }
+}
+
+impl From<ShaderError> for Diagnostic {
+    fn from(err: ShaderError) -> Self {
+        let message = match (&err.msg, &err.variant) {
+            (Some(msg), ShaderErrorVariant::PPD) => msg.to_owned(),
+            (Some(msg), ShaderErrorVariant::IO(io_err)) => format!("{msg} ({io_err})"),
+            (None, ShaderErrorVariant::IO(io_err)) => io_err.to_string(),
+            (Some(msg), variant) => format!("{msg}: {variant}"),
+            (None, variant) => variant.to_string(),
+        };
+
+        Self {
+            labels: err.label.into_iter().map(|label| *label).collect(),
+            notes: err.notes,
+            ..Self::error(message)
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    /// Renders the diagnostic similarly to `codespan-reporting`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.severity, self.message)?;
+
+        let gutter = self
+            .labels
+            .iter()
+            .filter_map(|label| label.position)
+            .map(|(line, _)| line.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        for (i, label) in self.labels.iter().enumerate() {
+            let Some((line, column)) = label.position else {
+                writeln!(f, "{:gutter$} ┌─ {}", "", label.path.display())?;
+                continue;
+            };
+
+            writeln!(
+                f,
+                "{:gutter$} ┌─ {}:{line}:{column}",
+                "",
+                label.path.display()
+            )?;
+
+            let Some(source_line) = label.source_line.as_ref() else {
+                continue;
+            };
+
+            let indent = source_line
+                .get(..column as usize - 1)
+                .map_or(0, |prefix| prefix.chars().count());
+            let marker = if i == 0 { "^" } else { "-" };
+
+            writeln!(f, "{:gutter$} │", "")?;
+            writeln!(f, "{line:>gutter$} │ {source_line}")?;
+            let underline = format!(
+                "{:indent$}{} {}",
+                "",
+                marker.repeat(label.length.max(1) as usize),
+                label.message.as_deref().unwrap_or_default(),
+            );
+            writeln!(f, "{:gutter$} │ {}", "", underline.trim_end())?;
+        }
+
+        for note in self.notes.iter() {
+            writeln!(f, "{:gutter$} = note: {note}", "")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+    use indoc::indoc;
+
+    #[test]
+    fn render_diagnostic() {
+        run_test(Test::new(
+            || {},
+            || {
+                let source_line = "//% includ \"lib/sdf\"\n";
+                let diagnostic = Diagnostic::error("Unrecognized preprocessor directive")
+                    .with_label(
+                        Label::span(
+                            Path::new("shader.wgsl"),
+                            12,
+                            source_line,
+                            &source_line[4..10],
+                        )
+                        .with_message("unknown directive"),
+                    )
+                    .with_note("expected one of `include`, `define`, `if`...");
+
+                assert_eq!(
+                    diagnostic.to_string(),
+                    indoc! {r#"
+                        error: Unrecognized preprocessor directive
+                           ┌─ shader.wgsl:12:5
+                           │
+                        12 │ //% includ "lib/sdf"
+                           │     ^^^^^^ unknown directive
+                           = note: expected one of `include`, `define`, `if`...
+                    "#}
+                );
+
+                let source = "(\n    fps_limit: Some(60)\n    window_title: \"Test\",\n)";
+                let err = ron::from_str::<ron::Value>(source).expect_err("RON should be invalid");
+                let diagnostic = Diagnostic::from_ron_error(&err, Path::new("config.ron"), source);
+                assert_eq!(
+                    diagnostic.to_string(),
+                    indoc! {r#"
+                        error: Expected comma
+                          ┌─ config.ron:3:5
+                          │
+                        3 │     window_title: "Test",
+                          │     ^
+                    "#}
+                );
+
+                // Columns are in bytes, markers stay under the character they point at
+                let source = "(window_title: \"Été ☀\" fps_limit: Some(60))";
+                let err = ron::from_str::<ron::Value>(source).expect_err("RON should be invalid");
+                let diagnostic = Diagnostic::from_ron_error(&err, Path::new("config.ron"), source);
+                assert_eq!(
+                    diagnostic.to_string(),
+                    indoc! {r#"
+                        error: Expected comma
+                          ┌─ config.ron:1:28
+                          │
+                        1 │ (window_title: "Été ☀" fps_limit: Some(60))
+                          │                        ^
+                    "#}
+                );
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,29 @@
+// Draws a texture over the whole target, used by the error overlay and to seed persistent
+// render targets. Kept out of the assets folder so it works even when those are broken
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.tex_coords = vec2f(f32(in_vertex_index & 2), f32((in_vertex_index << 1) & 2));
+    out.clip_position = vec4f(vec2f(2, -2) * out.tex_coords + vec2f(-1, 1), 0, 1);
+
+    return out;
+}
+
+@group(0) @binding(0)
+var t_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var s_texture: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_texture, s_texture, in.tex_coords);
+}
@@ -0,0 +1,110 @@
+use crate::shader_source::MemorySource;
+
+use std::path::{Path, PathBuf};
+
+/// WGSL generated from Rust, registered by `generate_wgsl_enum` and `generate_wgsl_struct`.
+///
+/// Shaders include it as `generated/<name>` without it having to exist on disk.
+pub struct GeneratedWgsl {
+    /// File name, e.g. `globals.wgsl`
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+inventory::collect!(GeneratedWgsl);
+
+/// Prefix of the include paths resolved to generated WGSL.
+pub const INCLUDE_PREFIX: &str = "generated/";
+
+/// Root of the paths of generated files in shader graphs, which can't clash with real files.
+const VIRTUAL_ROOT: &str = "<generated>";
+
+/// Every registered file, sorted by name.
+pub fn files() -> Vec<&'static GeneratedWgsl> {
+    let mut files = inventory::iter::<GeneratedWgsl>().collect::<Vec<_>>();
+    files.sort_by_key(|file| file.name);
+    files
+}
+
+/// Virtual path an include of generated WGSL refers to, whether or not it is registered.
+///
+/// `include` is relative to the including file, with its extension.
+pub fn resolve(include: &str) -> Option<PathBuf> {
+    let name = include.strip_prefix(INCLUDE_PREFIX)?;
+    Some(Path::new(VIRTUAL_ROOT).join(name))
+}
+
+/// Every registered file, at its virtual path.
+pub fn source() -> MemorySource {
+    files()
+        .into_iter()
+        .fold(MemorySource::new(), |source, file| {
+            source.with(Path::new(VIRTUAL_ROOT).join(file.name), file.source)
+        })
+}
+
+/// `content` as the generated file `name`, for WGSL that is only known at runtime.
+pub fn runtime_source(name: &str, content: &str) -> MemorySource {
+    MemorySource::new().with(Path::new(VIRTUAL_ROOT).join(name), content)
+}
+
+/// Writes every registered file and the `runtime` ones, as `(name, content)`, to `folder` so
+/// that editors can resolve the includes.
+pub fn write_all(folder: &Path, runtime: &[(&str, &str)]) -> std::io::Result<()> {
+    for file in files() {
+        write(folder, file.name, file.source)?;
+    }
+    for (name, content) in runtime {
+        write(folder, name, content)?;
+    }
+    Ok(())
+}
+
+/// Writes `content` to `file` in `folder`, unless it is up to date already so that file
+/// watchers don't fire.
+fn write(folder: &Path, file: &str, content: &str) -> std::io::Result<()> {
+    let path = folder.join(file);
+    if std::fs::read_to_string(&path).is_ok_and(|current| current == content) {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(folder)?;
+    std::fs::write(path, content)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        globals::Globals,
+        shader_source::{is_virtual, ShaderSource},
+        test::{run_test, Test},
+    };
+
+    #[test]
+    fn generated_registry() {
+        run_test(Test::new(
+            || {},
+            || {
+                let names = files().iter().map(|file| file.name).collect::<Vec<_>>();
+                assert_eq!(names, ["globals.wgsl", "mouse_state.wgsl"]);
+
+                let path = resolve("generated/globals.wgsl").expect("Include should be generated");
+                assert!(is_virtual(&path));
+                let source = source();
+                assert_eq!(source.read(&path).ok().as_deref(), Some(Globals::WGSL));
+
+                let path = resolve("generated/params.wgsl").expect("Include should be generated");
+                assert!(source.read(&path).is_err());
+                let runtime = runtime_source("params.wgsl", "struct Params {}");
+                assert_eq!(
+                    runtime.read(&path).ok().as_deref(),
+                    Some("struct Params {}")
+                );
+                assert_eq!(resolve("globals.wgsl"), None);
+                assert!(source.read(Path::new("globals.wgsl")).is_err());
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::mouse::{MouseData, MouseState};
+
+use {
+    bytemuck::{Pod, Zeroable},
+    macros::generate_wgsl_struct,
+};
+
+/// Standard uniform every shader can bind as `globals`, mirroring Shadertoy's inputs.
+///
+/// Bind it with `@group(G) @binding(B) var<uniform> globals: Globals;`, coordinates being in
+/// pixels from the top left corner.
+#[generate_wgsl_struct("globals.wgsl")]
+#[derive(Debug, Clone, Copy, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct Globals {
+    /// Size of the target in pixels, and the pixel aspect ratio (`iResolution`)
+    pub resolution: [f32; 3],
+    /// Scene time in seconds (`iTime`)
+    pub time: f32,
+    /// Scene time elapsed since the previous frame (`iTimeDelta`)
+    pub time_delta: f32,
+    /// Frames rendered since the last reset (`iFrame`)
+    pub frame: u32,
+    /// Frames per second of the clock (`iFrameRate`)
+    pub frame_rate: f32,
+    /// `Idle`, `Clicked` or `Held` and the button pressed, read with `mouse_state_tag` and
+    /// `mouse_state_button` of `generated/mouse_state.wgsl`
+    pub mouse_state: u32,
+    /// Position while dragging in `xy`, click position in `zw`, `z` being negative once
+    /// released and `w` only positive on the frame of the click (`iMouse`)
+    pub mouse: [f32; 4],
+    /// Position of the cursor, even when not pressed
+    pub cursor: [f32; 2],
+    /// Year, month (0-11), day (1-31) and seconds since midnight in UTC (`iDate`)
+    pub date: [f32; 4],
+    /// Size of the input textures of the pass in the order of their binding names, `w` being
+    /// 0 past the last one (`iChannelResolution`)
+    pub channel_resolution: [[f32; 4]; 4],
+}
+
+impl Globals {
+    /// Fills in the mouse fields from `data`, consuming its pending click.
+    pub fn set_mouse(&mut self, data: &mut MouseData) {
+        let pressed = !matches!(data.state, MouseState::Idle);
+        let click = data.click_pos;
+        let first_frame = std::mem::take(&mut data.new_click);
+
+        self.mouse_state = data.state.to_wgsl_u32();
+        self.mouse = [
+            data.drag_pos.x as f32,
+            data.drag_pos.y as f32,
+            if pressed { 1. } else { -1. } * click.x as f32,
+            if first_frame { 1. } else { -1. } * click.y as f32,
+        ];
+        self.cursor = [data.pos.x as f32, data.pos.y as f32];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    use std::mem::offset_of;
+
+    #[test]
+    fn globals_layout() {
+        run_test(Test::new(
+            || {},
+            || {
+                let module = naga::front::wgsl::parse_str(Globals::WGSL)
+                    .expect("Globals should be valid WGSL");
+                let mut layouter = naga::proc::Layouter::default();
+                layouter
+                    .update(module.to_ctx())
+                    .expect("Globals should have a layout");
+
+                let (handle, ty) = module
+                    .types
+                    .iter()
+                    .find(|(_, ty)| ty.name.as_deref() == Some("Globals"))
+                    .expect("Globals should be defined");
+                let naga::TypeInner::Struct { members, span } = &ty.inner else {
+                    panic!("Globals should be a struct");
+                };
+
+                assert_eq!(*span as usize, std::mem::size_of::<Globals>());
+                assert_eq!(layouter[handle].alignment.round_up(*span), *span);
+
+                let offsets = members
+                    .iter()
+                    .map(|member| member.offset as usize)
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    offsets,
+                    [
+                        offset_of!(Globals, resolution),
+                        offset_of!(Globals, time),
+                        offset_of!(Globals, time_delta),
+                        offset_of!(Globals, frame),
+                        offset_of!(Globals, frame_rate),
+                        offset_of!(Globals, mouse_state),
+                        offset_of!(Globals, mouse),
+                        offset_of!(Globals, cursor),
+                        offset_of!(Globals, date),
+                        offset_of!(Globals, channel_resolution),
+                    ]
+                );
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{
+    capture::save_png,
+    clock::ManualClock,
+    headless::OffscreenRenderer,
+    mouse::MouseState,
+    preprocessor::Defines,
+    render_graph::{PassConfig, PassKind},
+    utils::TimeConfig,
+    Config,
+};
+
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use {
+    anyhow::{anyhow, Context},
+    serde::Deserialize,
+    winit::{dpi::PhysicalPosition, event::MouseButton},
+};
+
+/// Folder of the golden tests, with the cases in `cases.ron`, the references in `references`
+/// and the images of failing cases in `failures`.
+pub const GOLDEN_FOLDER: &str = "tests/golden";
+
+/// A shader rendered at a fixed time and mouse state, compared to `references/<name>.png`.
+#[derive(Debug, Deserialize)]
+pub struct GoldenCase {
+    pub name: String,
+    /// Path of the shader relative to the golden folder, `.wgsl` may be omitted
+    pub shader: String,
+    #[serde(default)]
+    pub defines: Defines,
+    #[serde(default = "GoldenCase::default_size")]
+    pub size: (u32, u32),
+    /// Value of `globals.time`, in seconds
+    #[serde(default)]
+    pub time: f32,
+    /// Position of the mouse, in UV coordinates
+    #[serde(default)]
+    pub mouse: (f32, f32),
+    #[serde(default)]
+    pub mouse_state: GoldenMouseState,
+    /// Largest difference allowed in any channel, to absorb differences between GPUs
+    #[serde(default = "GoldenCase::default_tolerance")]
+    pub tolerance: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub enum GoldenMouseState {
+    #[default]
+    Idle,
+    Clicked,
+    Held,
+}
+
+impl GoldenCase {
+    fn default_size() -> (u32, u32) {
+        (128, 128)
+    }
+
+    fn default_tolerance() -> u8 {
+        2
+    }
+
+    fn reference_path(&self) -> PathBuf {
+        Path::new(GOLDEN_FOLDER)
+            .join("references")
+            .join(format!("{}.png", self.name))
+    }
+
+    /// Renders the shader of the case on its own, displayed as is.
+    pub async fn render(&self) -> anyhow::Result<image::RgbaImage> {
+        // The shader is looked up from the assets folder, which is ignored for absolute paths
+        let shader = Path::new(GOLDEN_FOLDER)
+            .join(&self.shader)
+            .with_extension("wgsl")
+            .canonicalize()
+            .with_context(|| format!("Shader `{}` of `{}` not found", self.shader, self.name))?;
+
+        let config = Config {
+            window_size: self.size,
+            time: TimeConfig {
+                start: self.time,
+                paused: true,
+                ..Default::default()
+            },
+            passes: vec![PassConfig {
+                name: self.name.clone(),
+                shader: shader.display().to_string(),
+                kind: PassKind::Render,
+                inputs: Default::default(),
+                outputs: Vec::new(),
+                defines: self.defines.clone(),
+                persistent: false,
+                seed: None,
+            }],
+            ..Default::default()
+        };
+
+        let clock = Rc::new(ManualClock::default());
+        let mut offscreen = OffscreenRenderer::new(&config, self.size, false, clock)
+            .await
+            .with_context(|| format!("Failed to render `{}`", self.name))?;
+
+        let mouse = &mut offscreen.renderer.mouse;
+        mouse.pos = PhysicalPosition::new(
+            (self.mouse.0 * self.size.0 as f32) as f64,
+            (self.mouse.1 * self.size.1 as f32) as f64,
+        );
+        mouse.state = match self.mouse_state {
+            GoldenMouseState::Idle => MouseState::Idle,
+            GoldenMouseState::Clicked => MouseState::Clicked(MouseButton::Left),
+            GoldenMouseState::Held => MouseState::Held(MouseButton::Left),
+        };
+        mouse.click_pos = mouse.pos;
+        mouse.drag_pos = mouse.pos;
+
+        Ok(offscreen
+            .render(true)?
+            .expect("Offscreen frame should be read back"))
+    }
+}
+
+pub fn load_cases() -> anyhow::Result<Vec<GoldenCase>> {
+    let path = Path::new(GOLDEN_FOLDER).join("cases.ron");
+    let file = std::fs::File::open(&path)
+        .with_context(|| format!("Failed to open '{}'", path.display()))?;
+    ron::de::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to parse '{}'", path.display()))
+}
+
+/// Pixels of an image that differ from its reference by more than the tolerance.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub message: String,
+    /// Actual image dimmed, with mismatching pixels in red, if the sizes match
+    pub diff: Option<image::RgbaImage>,
+}
+
+/// Compares `actual` to `reference` channel by channel.
+pub fn compare(
+    actual: &image::RgbaImage,
+    reference: &image::RgbaImage,
+    tolerance: u8,
+) -> Result<(), Mismatch> {
+    if actual.dimensions() != reference.dimensions() {
+        return Err(Mismatch {
+            message: format!(
+                "size is {:?} instead of {:?}",
+                actual.dimensions(),
+                reference.dimensions()
+            ),
+            diff: None,
+        });
+    }
+
+    let mut diff = image::RgbaImage::new(actual.width(), actual.height());
+    let mut mismatches = 0;
+    let mut max_delta = 0;
+    for ((pixel, expected), out) in actual
+        .pixels()
+        .zip(reference.pixels())
+        .zip(diff.pixels_mut())
+    {
+        let delta = pixel
+            .0
+            .iter()
+            .zip(expected.0)
+            .map(|(a, b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0);
+        max_delta = max_delta.max(delta);
+
+        *out = match delta > tolerance {
+            true => {
+                mismatches += 1;
+                image::Rgba([255, 0, 0, 255])
+            }
+            false => {
+                let [r, g, b, _] = pixel.0;
+                image::Rgba([r / 4, g / 4, b / 4, 255])
+            }
+        };
+    }
+
+    match mismatches {
+        0 => Ok(()),
+        _ => Err(Mismatch {
+            message: format!(
+                "{mismatches} pixels differ by more than {tolerance} (up to {max_delta})"
+            ),
+            diff: Some(diff),
+        }),
+    }
+}
+
+/// Renders every case and compares it to its reference, returning the failures.
+///
+/// The image of a failing case and its diff get written to `failures`.
+pub async fn check() -> anyhow::Result<Vec<String>> {
+    let failures_folder = Path::new(GOLDEN_FOLDER).join("failures");
+    let mut failures = Vec::new();
+
+    for case in load_cases()? {
+        let actual = case.render().await?;
+
+        let mismatch = match image::open(case.reference_path()) {
+            Ok(reference) => match compare(&actual, &reference.to_rgba8(), case.tolerance) {
+                Ok(()) => continue,
+                Err(mismatch) => mismatch,
+            },
+            Err(err) => Mismatch {
+                message: format!(
+                    "no reference ({err}), run `cargo run -- --bless {}`",
+                    case.name
+                ),
+                diff: None,
+            },
+        };
+
+        std::fs::create_dir_all(&failures_folder)?;
+        save_png(
+            &actual,
+            &failures_folder.join(format!("{}.png", case.name)),
+            &[],
+        )?;
+        if let Some(diff) = mismatch.diff.as_ref() {
+            save_png(
+                diff,
+                &failures_folder.join(format!("{}.diff.png", case.name)),
+                &[],
+            )?;
+        }
+        failures.push(format!("`{}`: {}", case.name, mismatch.message));
+    }
+
+    Ok(failures)
+}
+
+/// Renders the cases named in `names`, or all of them if empty, as their new references.
+pub async fn bless(names: &[String]) -> anyhow::Result<()> {
+    let cases = load_cases()?;
+    if let Some(unknown) = names
+        .iter()
+        .find(|name| !cases.iter().any(|case| &case.name == *name))
+    {
+        return Err(anyhow!("Unknown golden case `{unknown}`"));
+    }
+
+    for case in cases
+        .iter()
+        .filter(|case| names.is_empty() || names.contains(&case.name))
+    {
+        let path = case.reference_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        save_png(&case.render().await?, &path, &[])?;
+        println!("Blessed {}", path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    #[test]
+    fn compare_images() {
+        run_test(Test::new(
+            || {},
+            || {
+                let reference = image::RgbaImage::from_pixel(4, 2, image::Rgba([100, 50, 0, 255]));
+
+                let mut close = reference.clone();
+                close.put_pixel(1, 1, image::Rgba([102, 48, 0, 255]));
+                assert!(compare(&close, &reference, 2).is_ok());
+
+                let mut off = close.clone();
+                off.put_pixel(3, 0, image::Rgba([100, 50, 0, 250]));
+                let mismatch = compare(&off, &reference, 2).unwrap_err();
+                assert_eq!(mismatch.message, "1 pixels differ by more than 2 (up to 5)");
+                let diff = mismatch.diff.unwrap();
+                assert_eq!(diff.get_pixel(3, 0), &image::Rgba([255, 0, 0, 255]));
+                assert_eq!(diff.get_pixel(1, 1), &image::Rgba([25, 12, 0, 255]));
+
+                let small = image::RgbaImage::new(2, 2);
+                assert!(compare(&small, &reference, 2).unwrap_err().diff.is_none());
+            },
+            || {},
+        ));
+    }
+
+    /// Catches regressions of the WGSL library, rebless with `cargo run -- --bless` after
+    /// intended changes.
+    #[test]
+    fn golden_images() {
+        run_test(Test::new(
+            || {},
+            || {
+                let failures = pollster::block_on(check()).expect("Golden cases should render");
+                assert!(
+                    failures.is_empty(),
+                    "Golden images differ, see {GOLDEN_FOLDER}/failures:\n{}",
+                    failures.join("\n")
+                );
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{
+    capture::{save_png, Readback},
+    clock::{FixedStepClock, SharedClock},
+    renderer::Renderer,
+    Config,
+};
+
+use std::{ops::Range, path::PathBuf, rc::Rc};
+
+use anyhow::{anyhow, bail, Context};
+
+/// Format of the offscreen target, which is also the layout of the written PNGs.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+pub struct HeadlessOptions {
+    /// Resolution of the frames, defaults to the window size of the config
+    pub size: Option<(u32, u32)>,
+    /// Frames to write, frame `n` being rendered at `n / fps` seconds
+    pub frames: Range<u32>,
+    pub fps: f32,
+    /// Folder the frames are written to, as `frame_00042.png`
+    pub output_folder: PathBuf,
+    /// Use the software adapter even if a GPU is available
+    pub force_fallback_adapter: bool,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            size: None,
+            frames: 0..1,
+            fps: 60.,
+            output_folder: PathBuf::from("frames"),
+            force_fallback_adapter: false,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    /// Parses the arguments of the headless mode, starting with `--headless`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut args = args.iter().map(String::as_str);
+        if args.next() != Some("--headless") {
+            return Err("Expected `--headless` as first argument".to_owned());
+        }
+
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for `{arg}`"))
+            };
+            let invalid = |value: &str| format!("Invalid value `{value}` for `{arg}`");
+
+            match arg {
+                "--frames" => {
+                    let value = value()?;
+                    let (start, end) = value
+                        .split_once("..")
+                        .and_then(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)))
+                        .filter(|(start, end)| start < end)
+                        .ok_or_else(|| invalid(value))?;
+                    options.frames = start..end;
+                }
+                "--size" => {
+                    let value = value()?;
+                    options.size = Some(
+                        value
+                            .split_once('x')
+                            .and_then(|(width, height)| {
+                                Some((width.parse().ok()?, height.parse().ok()?))
+                            })
+                            .filter(|&(width, height)| width > 0 && height > 0)
+                            .ok_or_else(|| invalid(value))?,
+                    );
+                }
+                "--fps" => {
+                    let value = value()?;
+                    options.fps = value
+                        .parse()
+                        .ok()
+                        .filter(|fps: &f32| *fps > 0.)
+                        .ok_or_else(|| invalid(value))?;
+                }
+                "--output" => options.output_folder = value()?.into(),
+                "--fallback" => options.force_fallback_adapter = true,
+                _ => return Err(format!("Unknown argument `{arg}`")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Creates a device without a surface, falling back to the software adapter if there is no GPU.
+pub(crate) async fn request_device(
+    force_fallback_adapter: bool,
+) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    // Any backend will do since nothing gets presented
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+
+    let adapter_options = |force_fallback_adapter| wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: None,
+        force_fallback_adapter,
+    };
+    let adapter = match instance
+        .request_adapter(&adapter_options(force_fallback_adapter))
+        .await
+    {
+        Some(adapter) => adapter,
+        // Machines without a GPU may still have a software adapter
+        None => instance
+            .request_adapter(&adapter_options(true))
+            .await
+            .ok_or_else(|| anyhow!("No adapter available"))?,
+    };
+    println!("Rendering with {}", adapter.get_info().name);
+
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                memory_hints: wgpu::MemoryHints::Performance,
+                required_features: wgpu::Features::empty(),
+                // Software adapters don't always reach the default limits
+                required_limits: adapter.limits(),
+                label: None,
+            },
+            None,
+        )
+        .await
+        .context("Should find compatible device")
+}
+
+/// A renderer drawing into an offscreen texture that gets read back.
+pub(crate) struct OffscreenRenderer {
+    pub renderer: Renderer,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    readback: Readback,
+}
+
+impl OffscreenRenderer {
+    /// Creates a device with `request_device`, and fails if any shader of `config` doesn't
+    /// compile.
+    pub async fn new(
+        config: &Config,
+        (width, height): (u32, u32),
+        force_fallback_adapter: bool,
+        clock: SharedClock,
+    ) -> anyhow::Result<Self> {
+        if width == 0 || height == 0 {
+            bail!("Invalid resolution {width}x{height}");
+        }
+
+        let (device, queue) = request_device(force_fallback_adapter).await?;
+
+        let target = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: FORMAT,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: Vec::new(),
+            desired_maximum_frame_latency: 1,
+        };
+        let renderer =
+            Renderer::new(device, queue, target, config, clock).map_err(|err| anyhow!(err))?;
+        if !renderer.is_valid() {
+            bail!("Shaders failed to compile:\n{}", renderer.error_text());
+        }
+
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: renderer.target.usage,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let readback = Readback::new(&renderer.device, &texture)?;
+
+        Ok(Self {
+            renderer,
+            texture,
+            view,
+            readback,
+        })
+    }
+
+    /// Renders the next frame at the current time of the clock, reading it back if `read` is
+    /// set.
+    pub fn render(&mut self, read: bool) -> anyhow::Result<Option<image::RgbaImage>> {
+        let renderer = &mut self.renderer;
+        renderer.update();
+
+        let mut encoder = renderer
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Encoder"),
+            });
+        renderer.render(&mut encoder, &self.view);
+        if read {
+            self.readback.copy(&mut encoder, &self.texture);
+        }
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+        renderer.end_frame();
+
+        read.then(|| self.readback.read(&renderer.device))
+            .transpose()
+    }
+}
+
+/// Renders the passes of `config` offscreen with a fixed timestep and writes the requested
+/// frames to numbered PNGs.
+///
+/// Every frame before the requested range is rendered too, so that persistent targets end
+/// up in the same state as they would when rendering from the start.
+pub async fn render_headless(config: &Config, options: &HeadlessOptions) -> anyhow::Result<()> {
+    let size = options.size.unwrap_or(config.window_size);
+    let clock = Rc::new(FixedStepClock::new(options.fps as f64));
+    let mut offscreen =
+        OffscreenRenderer::new(config, size, options.force_fallback_adapter, clock).await?;
+
+    std::fs::create_dir_all(&options.output_folder).with_context(|| {
+        format!(
+            "Failed to create output folder '{}'",
+            options.output_folder.display()
+        )
+    })?;
+
+    for frame in 0..options.frames.end {
+        let Some(image) = offscreen.render(options.frames.contains(&frame))? else {
+            continue;
+        };
+
+        let path = options.output_folder.join(format!("frame_{frame:05}.png"));
+        save_png(&image, &path, &offscreen.renderer.metadata(None))?;
+        println!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    #[test]
+    fn headless_options() {
+        run_test(Test::new(
+            || {},
+            || {
+                let parse = |args: &str| {
+                    let args = args
+                        .split_whitespace()
+                        .map(str::to_owned)
+                        .collect::<Vec<_>>();
+                    HeadlessOptions::from_args(&args)
+                };
+
+                let options = parse("--headless --frames 3..5 --size 320x240 --fps 30 --fallback")
+                    .expect("Valid options should parse");
+                assert_eq!(options.frames, 3..5);
+                assert_eq!(options.size, Some((320, 240)));
+                assert_eq!(options.fps, 30.);
+                assert!(options.force_fallback_adapter);
+
+                assert_eq!(
+                    parse("--headless --frames 5..3").err().as_deref(),
+                    Some("Invalid value `5..3` for `--frames`")
+                );
+                assert_eq!(
+                    parse("--headless --size 0x").err().as_deref(),
+                    Some("Invalid value `0x` for `--size`")
+                );
+                assert_eq!(
+                    parse("--headless --fps 0").err().as_deref(),
+                    Some("Invalid value `0` for `--fps`")
+                );
+                assert_eq!(
+                    parse("--headless --output").err().as_deref(),
+                    Some("Missing value for `--output`")
+                );
+                assert_eq!(
+                    parse("--headless --loop").err().as_deref(),
+                    Some("Unknown argument `--loop`")
+                );
+                assert!(parse("--frames 0..1").is_err());
+            },
+            || {},
+        ));
+    }
+}
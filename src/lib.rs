@@ -5,19 +5,50 @@
 //! # wgsl_playground
 //! Simple WGSL shader hot-reloading playground.
 
+mod capture;
+mod clock;
+mod diagnostic;
+mod generated;
+mod globals;
+mod golden;
+mod headless;
 mod mouse;
+mod overlay;
+mod params;
+mod preprocessor;
+mod reflection;
+mod render_graph;
+mod renderer;
 mod shader_graph;
+mod shader_source;
+mod shadertoy;
+mod std_lib;
 mod texture;
 mod timer;
 mod utils;
 
+pub use {
+    diagnostic::Diagnostic,
+    golden::bless as bless_golden,
+    headless::{render_headless, HeadlessOptions},
+    shadertoy::import_shadertoy,
+};
+
 use {
-    mouse::{Mouse, MouseData, MouseUniform},
-    texture::{Texture, TexturePair},
-    utils::{FileWatcher, SceneTime},
+    clock::RealClock,
+    diagnostic::Label,
+    mouse::MouseData,
+    overlay::ErrorOverlay,
+    renderer::Renderer,
+    texture::Texture,
+    utils::{FileWatcher, SceneTime, TimeConfig},
 };
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use {
     bytemuck::Zeroable,
@@ -61,35 +92,42 @@ pub trait Updateable {
     fn update(&mut self, queue: &wgpu::Queue);
 }
 
-struct Pipeline {
-    shader: shader_graph::ShaderGraph,
-    pipeline: wgpu::RenderPipeline,
-    layout: wgpu::PipelineLayout,
-}
+/// Keys jumping to the bookmarks of the time config, in order.
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
 
 struct State<'a> {
     window: &'a Window,
     surface: wgpu::Surface<'a>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    surface_config: wgpu::SurfaceConfiguration,
     size: PhysicalSize<u32>,
 
-    assets_folder: std::path::PathBuf,
+    renderer: Renderer,
 
-    render_pipelines: Vec<Pipeline>,
-    blit_pipeline: Pipeline,
+    file_watcher: FileWatcher,
 
-    texture_pair: TexturePair,
+    error_overlay: ErrorOverlay,
 
-    file_watcher: FileWatcher,
+    /// Config currently applied, reloaded when its file changes
+    config: Config,
+    /// Why the last change of the config couldn't be applied
+    config_error: Option<Diagnostic>,
 
-    time: SceneTime,
-    mouse: Mouse,
+    /// Whether a screenshot should be taken of the next frame
+    screenshot_requested: bool,
 }
 
 impl<'a> State<'a> {
-    async fn new(window: &'a Window, config: &Config) -> Self {
+    /// `config_error` is why the config couldn't be read, `config` then being the default one.
+    async fn new(window: &'a Window, config: Config, config_error: Option<Diagnostic>) -> Self {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -149,169 +187,36 @@ impl<'a> State<'a> {
             desired_maximum_frame_latency: 2,
         };
 
-        let assets_folder = Path::new(&config.assets_folder).to_path_buf();
-
-        // FBOs
-        let texture_pair = TexturePair::new(&device, &surface_config);
-
-        // Time uniform
-        let time = SceneTime::new(&device);
-
-        // Mouse
-        let mouse = Mouse::new(&device, MouseData::new(1000));
-
-        // Render pipeline
-        let render_pipelines = config
-            .shader_paths
-            .iter()
-            .map(|path| {
-                let path = match &path[path.len() - 5..] {
-                    ".wgsl" => path.to_owned(),
-                    _ => format!("{path}.wgsl"),
-                };
-
-                let render_pipeline_shader =
-                    shader_graph::ShaderGraph::try_from_final(assets_folder.join(&path).as_path())
-                        .unwrap_or_else(|_| {
-                            panic!("Shader code should be available at path '{path}'")
-                        });
-
-                let render_pipeline_layout =
-                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                        label: Some("Render Pipeline Layout"),
-                        bind_group_layouts: &[
-                            &texture_pair.get().0.bind_group_layout,
-                            &time.bind_group_layout,
-                            &mouse.bind_group_layout,
-                        ],
-                        push_constant_ranges: &[],
-                    });
-
-                Pipeline {
-                    pipeline: Self::create_render_pipeline(
-                        &device,
-                        &surface_config,
-                        &render_pipeline_shader,
-                        &render_pipeline_layout,
-                        format!("Render Pipeline ({path})").as_str(),
-                    )
-                    .unwrap_or_else(|_| panic!("Shader should compile ({path})")),
-                    shader: render_pipeline_shader,
-                    layout: render_pipeline_layout,
-                }
-            })
-            .collect::<Vec<_>>();
-
-        // Render pipeline
-        let blit_pipeline_shader =
-            shader_graph::ShaderGraph::try_from_final(assets_folder.join("blit.wgsl").as_path())
-                .expect("Shader code should be available at path");
-        let blit_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&texture_pair.get().0.bind_group_layout],
-            push_constant_ranges: &[],
+        // Stays empty until the config is fixed if its graph is invalid
+        let mut renderer = Renderer::empty(
+            device,
+            queue,
+            surface_config,
+            &config.time,
+            Rc::new(RealClock::new()),
+        );
+        let graph_error = renderer.reconfigure(&config).err().map(|err| {
+            let diagnostic = Diagnostic::error(err).with_label(Label::file(&config.path));
+            eprint!("{diagnostic}");
+            diagnostic
         });
 
-        let blit_pipeline = Pipeline {
-            pipeline: Self::create_render_pipeline(
-                &device,
-                &surface_config,
-                &blit_pipeline_shader,
-                &blit_pipeline_layout,
-                "Blit Pipeline",
-            )
-            .expect("Shader should compile"),
-            shader: blit_pipeline_shader,
-            layout: blit_pipeline_layout,
-        };
+        let error_overlay = ErrorOverlay::new(&renderer.device, renderer.target.format, size);
 
-        // File Watcher
-        let mut file_watcher = FileWatcher::init();
-        for path in render_pipelines
-            .iter()
-            .flat_map(|pipeline| pipeline.shader.paths())
-        {
-            file_watcher.watch(path);
-        }
-        file_watcher.watch(assets_folder.join("blit.wgsl").as_path());
-
-        Self {
+        let mut state = Self {
             window,
             surface,
-            device,
-            queue,
-            surface_config,
             size,
-            assets_folder,
-            render_pipelines,
-            blit_pipeline,
-            texture_pair,
-            file_watcher,
-            time,
-            mouse,
-        }
-    }
-
-    fn create_render_pipeline(
-        device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-        shader_graph: &shader_graph::ShaderGraph,
-        render_pipeline_layout: &wgpu::PipelineLayout,
-        label: &str,
-    ) -> Result<wgpu::RenderPipeline, wgpu::CompilationInfo> {
-        let shader_code = shader_graph
-            .finish()
-            .expect("Shader code should compile successfully");
-
-        device.push_error_scope(wgpu::ErrorFilter::Validation);
-        let mut shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(shader_code.into()),
-        });
-        if let Some(_) = pollster::block_on(device.pop_error_scope()) {
-            let comp_info = pollster::block_on(shader.get_compilation_info());
-            return Err(comp_info);
-        }
-
-        Ok(
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                cache: None,
-                label: Some(label),
-                layout: Some(render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    compilation_options: Default::default(),
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    compilation_options: Default::default(),
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            }),
-        )
+            renderer,
+            file_watcher: FileWatcher::init(),
+            error_overlay,
+            config,
+            config_error: config_error.or(graph_error),
+            screenshot_requested: false,
+        };
+        state.update_error_overlay();
+        state.sync_file_watcher();
+        state
     }
 
     pub fn window(&self) -> &Window {
@@ -321,174 +226,303 @@ impl<'a> State<'a> {
     fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
-            self.surface_config.width = new_size.width;
-            self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+            self.renderer.resize(new_size.width, new_size.height);
+            self.surface
+                .configure(&self.renderer.device, &self.renderer.target);
 
-            self.texture_pair = TexturePair::new(&self.device, &self.surface_config);
+            self.error_overlay
+                .resize(&self.renderer.device, &self.renderer.queue, new_size);
         }
     }
 
+    /// Handles the key bindings:
+    /// - R: reset persistent targets
+    /// - F12: screenshot
+    /// - Space: pause/resume
+    /// - Period/Comma: step forward/back, can be held
+    /// - Home: rewind to zero
+    /// - 1-9: jump to a bookmark of the time config
+    /// - BracketRight/BracketLeft: double/halve the playback speed
+    /// - ArrowRight/ArrowLeft: select the next/previous component of the params
+    /// - ArrowUp/ArrowDown: increment/decrement the selected component, can be held
     fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(code),
+                    repeat,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+
+        let bookmark = BOOKMARK_KEYS.iter().position(|key| key == code);
+        let time = &mut self.renderer.time;
+        let params = &mut self.renderer.params;
+        match code {
+            KeyCode::KeyR if !repeat => self.renderer.reset(),
+            KeyCode::F12 if !repeat => self.screenshot_requested = true,
+            KeyCode::Space if !repeat => time.set_paused(!time.is_paused()),
+            KeyCode::Period => time.step(1),
+            KeyCode::Comma => time.step(-1),
+            KeyCode::Home if !repeat => time.seek(0.),
+            KeyCode::BracketRight if !repeat => time.set_speed(time.speed() * 2.),
+            KeyCode::BracketLeft if !repeat => time.set_speed(time.speed() / 2.),
+            KeyCode::ArrowRight => params.select(1),
+            KeyCode::ArrowLeft => params.select(-1),
+            KeyCode::ArrowUp => params.adjust(1),
+            KeyCode::ArrowDown => params.adjust(-1),
+            _ if let Some(index) = bookmark
+                && !repeat =>
+            {
+                match time.bookmarks.get(index) {
+                    Some(&bookmark) => time.seek(bookmark as f64),
+                    None => println!("No bookmark {}", index + 1),
+                }
+            }
+            _ => return false,
+        }
+
+        match code {
+            KeyCode::KeyR | KeyCode::F12 => {}
+            KeyCode::ArrowRight | KeyCode::ArrowLeft | KeyCode::ArrowUp | KeyCode::ArrowDown => {
+                println!("{}", self.renderer.params)
+            }
+            _ => println!("{}", self.renderer.time),
+        }
+        true
     }
 
     fn update(&mut self) {
-        let delta_time = self.time.previous_update.elapsed().as_secs_f32();
+        let time = &mut self.renderer.time;
+        let delta_time = time.since_update().as_secs_f32();
 
         // FPS logging
-        self.time.deltas_last_second.push(delta_time);
-        let sum_deltas = self.time.deltas_last_second.iter().sum::<f32>();
+        time.deltas_last_second.push(delta_time);
+        let sum_deltas = time.deltas_last_second.iter().sum::<f32>();
         if sum_deltas > 1. {
-            let deltas = self.time.deltas_last_second.len();
+            let deltas = time.deltas_last_second.len();
             println!(
                 "fps: {} ({deltas} / {sum_deltas})",
                 deltas as f32 / sum_deltas,
             );
-            self.time.deltas_last_second.clear();
+            time.deltas_last_second.clear();
         }
 
-        // Time
-        self.queue.write_buffer(
-            &self.time.buffer,
-            0,
-            bytemuck::cast_slice(&[self.time.start.elapsed().as_secs_f32()]),
-        );
-        self.time.previous_update = std::time::Instant::now();
-
-        // Mouse
-        self.mouse.update(&self.queue);
-        let mut data = MouseUniform::new(
-            &self.mouse.data,
-            glam::Vec2::new(self.size.width as f32, self.size.height as f32),
-        );
-        self.queue
-            .write_buffer(&self.mouse.buffer, 0, bytemuck::cast_slice(&[data]));
+        time.previous_update = time.clock.now();
+        self.renderer.update();
 
         // File watcher
-        if let Ok(updated_paths) = self.file_watcher.event_receiver.try_recv() {
+        if let Ok(mut updated_paths) = self.file_watcher.event_receiver.try_recv() {
             // Drain channel
-            while self.file_watcher.event_receiver.try_recv().is_ok() {}
-
-            for pipeline in self.render_pipelines.iter_mut() {
-                let last = pipeline
-                    .shader
-                    .last()
-                    .expect("Shader should have at least one node at this point");
-
-                if !&updated_paths.contains(&last.path.canonicalize().unwrap_or_else(|_| {
-                    panic!(
-                        "Shader path should be canonicalizable: '{}'",
-                        last.path.to_str().unwrap()
-                    )
-                })) {
-                    continue;
-                }
+            while let Ok(paths) = self.file_watcher.event_receiver.try_recv() {
+                updated_paths.extend(paths);
+            }
+
+            if self
+                .config
+                .path
+                .canonicalize()
+                .is_ok_and(|path| updated_paths.contains(&path))
+            {
+                self.reload_config();
+            }
+            self.renderer.reload(&updated_paths);
+            self.update_error_overlay();
+
+            // Includes may have been added or removed by the reload
+            self.sync_file_watcher();
+        }
+    }
+
+    /// Shows the config error, if any, followed by the diagnostics of the pipelines.
+    fn update_error_overlay(&mut self) {
+        let config_error = self.config_error.iter().map(Diagnostic::to_string);
+        self.error_overlay.set_text(
+            &self.renderer.device,
+            &self.renderer.queue,
+            config_error.chain([self.renderer.error_text()]).collect(),
+        );
+    }
+
+    /// Watches the config along with the files of every pipeline.
+    fn sync_file_watcher(&mut self) {
+        let config_path = self.config.path.canonicalize().ok();
+        self.file_watcher
+            .sync(self.renderer.paths().into_iter().chain(config_path));
+    }
+
+    /// Reads the config again and applies what changed, keeping the current one if the new one
+    /// is invalid.
+    fn reload_config(&mut self) {
+        let config = read_config(&self.config.path).and_then(|config| self.apply_config(config));
 
-                let shader = shader_graph::ShaderGraph::try_from_final(last.path.as_path())
-                    .expect("Shader graph should be possible to build");
-
-                if let Ok(new_pipeline) = Self::create_render_pipeline(
-                    &self.device,
-                    &self.surface_config,
-                    &shader,
-                    &pipeline.layout,
-                    format!(
-                        "Render Pipeline ({})",
-                        last.path
-                            .to_str()
-                            .expect("Last node path should already be to be valid")
-                    )
-                    .as_str(),
-                ) {
-                    pipeline.pipeline = new_pipeline;
+        match config {
+            Ok(()) => {
+                if self.config_error.take().is_some() {
+                    println!("Config applied successfully");
                 }
             }
+            Err(diagnostic) => {
+                eprint!("{diagnostic}");
+                self.config_error = Some(diagnostic);
+            }
         }
     }
 
+    /// Applies the differences between `config` and the current one, without restarting time
+    /// or clearing the targets that still exist.
+    fn apply_config(&mut self, config: Config) -> Result<(), Diagnostic> {
+        let old = &self.config;
+        // An empty renderer never got the graph of the current config applied
+        let graph_changed = self.renderer.is_empty()
+            || config.assets_folder() != old.assets_folder()
+            || config.include_paths != old.include_paths
+            || config.passes != old.passes
+            || config.output != old.output
+            || config.buffers != old.buffers
+            || config.params != old.params;
+        if graph_changed {
+            self.renderer
+                .reconfigure(&config)
+                .map_err(|err| Diagnostic::error(err).with_label(Label::file(&config.path)))?;
+        }
+
+        if config.window_title != old.window_title {
+            self.window.set_title(&config.window_title);
+        }
+        if config.window_size != old.window_size {
+            let (width, height) = config.window_size;
+            // Resized events take care of the surface, if the window manager complies
+            let _ = self
+                .window
+                .request_inner_size(PhysicalSize::new(width, height));
+        }
+        if config.time != old.time {
+            self.renderer.time.reconfigure(&old.time, &config.time);
+        }
+
+        self.config = config;
+        Ok(())
+    }
+
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let output_view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-
-        // Intermediate renders
-        for render_pipeline in self.render_pipelines.iter() {
-            {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Intermediate Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &self.texture_pair.get().1.texture.view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::RED),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
+        let mut encoder =
+            self.renderer
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
                 });
 
-                render_pass.set_pipeline(&render_pipeline.pipeline);
-
-                render_pass.set_bind_group(0, &self.texture_pair.get().0.bind_group, &[]);
-                render_pass.set_bind_group(1, &self.time.bind_group, &[]);
-                render_pass.set_bind_group(2, &self.mouse.bind_group, &[]);
-
-                render_pass.draw(0..3, 0..1);
-            }
-
-            self.texture_pair.swap();
-        }
-
-        // Blit
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Blit Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &output_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLUE),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
+        self.renderer.render(&mut encoder, &output_view);
+        let capture = std::mem::take(&mut self.screenshot_requested)
+            .then(|| {
+                self.renderer
+                    .capture(&mut encoder, self.config.screenshot_output.as_deref())
+            })
+            .and_then(|capture| {
+                capture
+                    .inspect_err(|err| eprintln!("Failed to take screenshot: {err}"))
+                    .ok()
             });
+        self.error_overlay.render(&mut encoder, &output_view);
 
-            render_pass.set_pipeline(&self.blit_pipeline.pipeline);
-
-            render_pass.set_bind_group(0, &self.texture_pair.get().0.bind_group, &[]);
+        self.renderer
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+        self.renderer.end_frame();
+        output.present();
 
-            render_pass.draw(0..3, 0..1);
+        if let Some(capture) = capture {
+            let path = self
+                .config
+                .path
+                .with_file_name(format!("screenshot_{}.png", capture::timestamp()));
+            match capture.save(&self.renderer.device, &path) {
+                Ok(()) => println!("Saved screenshot to {}", path.display()),
+                Err(err) => eprintln!("Failed to save screenshot: {err}"),
+            }
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-
         Ok(())
     }
 }
 
-#[derive(Deserialize)]
+/// Path of the config unless another one is given, relative to the working directory.
+pub const CONFIG_PATH: &str = "config.ron";
+
+/// Reads and parses the config at `path`.
+pub fn read_config(path: &Path) -> Result<Config, Diagnostic> {
+    let source = std::fs::read_to_string(path).map_err(|err| {
+        Diagnostic::error(format!("Failed to read config: {err}")).with_label(Label::file(path))
+    })?;
+    ron::from_str::<Config>(&source)
+        .map(|config| config.with_path(path))
+        .map_err(|err| Diagnostic::from_ron_error(&err, path, &source))
+}
+
+/// Writes the generated WGSL to `folder` for editors, with the `params.wgsl` of `config` if it
+/// declares params.
+pub fn emit_generated(folder: &Path, config: Option<&Config>) -> anyhow::Result<()> {
+    let params = config
+        .map(|config| params::Params::new(&config.params))
+        .transpose()
+        .map_err(anyhow::Error::msg)?;
+    let params = params
+        .filter(|params| !params.is_empty())
+        .map(|params| params.wgsl());
+
+    generated::write_all(
+        folder,
+        params
+            .as_deref()
+            .map(|params| ("params.wgsl", params))
+            .as_slice(),
+    )?;
+    Ok(())
+}
+
+#[derive(Clone, Deserialize)]
 pub struct Config {
     window_size: (u32, u32),
     window_title: String,
 
     fps_limit: Option<u32>,
 
+    /// File the config was read from, `CONFIG_PATH` by default
+    #[serde(skip, default = "Config::default_path")]
+    path: PathBuf,
+    /// Folder of the shaders and images, relative to the folder of the config
     assets_folder: String,
-    shader_paths: Vec<String>,
+    /// Folders `<...>` includes are looked up in before the assets folder and the standard
+    /// library, relative to the assets folder
+    #[serde(default)]
+    include_paths: Vec<String>,
+    /// Passes of the render graph, in any order
+    passes: Vec<render_graph::PassConfig>,
+    /// Output displayed in the window, defaults to the first output of the last pass
+    #[serde(default)]
+    output: Option<String>,
+    /// Storage buffers available to every pass, with their size in bytes
+    #[serde(default)]
+    buffers: BTreeMap<String, u64>,
+    /// Output saved by screenshots (F12), defaults to the final image
+    #[serde(default)]
+    screenshot_output: Option<String>,
+    #[serde(default)]
+    time: TimeConfig,
+    /// Values shaders can bind as `params`, adjusted with the arrow keys
+    #[serde(default)]
+    params: Vec<params::ParamConfig>,
 }
 
 impl Default for Config {
@@ -497,13 +531,55 @@ impl Default for Config {
             window_size: (600, 600),
             window_title: "WGSL Playground".to_string(),
             fps_limit: Some(60),
+            path: Self::default_path(),
             assets_folder: "assets".to_string(),
-            shader_paths: vec!["shader".to_string()],
+            include_paths: Vec::new(),
+            passes: vec![render_graph::PassConfig {
+                name: "shader".to_string(),
+                shader: "shader".to_string(),
+                inputs: [(
+                    "t_diffuse".to_string(),
+                    render_graph::Input::Previous("shader".to_string()),
+                )]
+                .into(),
+                kind: render_graph::PassKind::Render,
+                outputs: Vec::new(),
+                defines: preprocessor::Defines::new(),
+                persistent: false,
+                seed: None,
+            }],
+            output: None,
+            buffers: BTreeMap::new(),
+            screenshot_output: None,
+            time: TimeConfig::default(),
+            params: Vec::new(),
         }
     }
 }
 
-pub async fn run(config: Config) {
+impl Config {
+    /// The same config, as read from `path`.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    fn default_path() -> PathBuf {
+        PathBuf::from(CONFIG_PATH)
+    }
+
+    /// Assets folder, resolved from the folder of the config.
+    fn assets_folder(&self) -> PathBuf {
+        self.path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&self.assets_folder)
+    }
+}
+
+/// Opens the window and renders `config` until it gets closed, showing `config_error` in the
+/// overlay until the config is fixed.
+pub async fn run(config: Config, config_error: Option<Diagnostic>) {
     env_logger::init();
 
     let event_loop = EventLoop::new().unwrap();
@@ -516,12 +592,12 @@ pub async fn run(config: Config) {
         .build(&event_loop)
         .unwrap();
 
-    let mut state = State::new(&window, &config).await;
+    let mut state = State::new(&window, config, config_error).await;
 
     event_loop
         .run(move |event, control_flow| {
-            let delta = state.time.previous_update.elapsed().as_secs_f32();
-            if let Some(fps_limit) = config.fps_limit
+            let delta = state.renderer.time.since_update().as_secs_f32();
+            if let Some(fps_limit) = state.config.fps_limit
                 && delta < 1. / fps_limit as f32
             {
                 std::thread::sleep(std::time::Duration::from_secs_f32(
@@ -551,7 +627,7 @@ fn handle_event(state: &mut State<'_>, event: Event<()>, control_flow: &EventLoo
                     ..
                 } => control_flow.exit(),
                 WindowEvent::MouseInput { .. } | WindowEvent::CursorMoved { .. } => {
-                    state.mouse.process_events(event);
+                    state.renderer.mouse.process_events(event);
                 }
                 WindowEvent::Resized(physical_size) => {
                     state.resize(*physical_size);
@@ -580,17 +656,17 @@ fn handle_event(state: &mut State<'_>, event: Event<()>, control_flow: &EventLoo
 #[cfg(test)]
 mod test {
     pub(crate) struct Test {
-        pub setup: Box<dyn FnOnce() -> ()>,
-        pub test: Box<dyn FnOnce() -> () + std::panic::UnwindSafe>,
-        pub cleanup: Box<dyn FnOnce() -> ()>,
+        pub setup: Box<dyn FnOnce()>,
+        pub test: Box<dyn FnOnce() + std::panic::UnwindSafe>,
+        pub cleanup: Box<dyn FnOnce()>,
     }
 
     impl Test {
         pub(crate) fn new<S, T, C>(setup: S, test: T, cleanup: C) -> Self
         where
-            S: FnOnce() -> () + 'static,
-            T: FnOnce() -> () + std::panic::UnwindSafe + 'static,
-            C: FnOnce() -> () + 'static,
+            S: FnOnce() + 'static,
+            T: FnOnce() + std::panic::UnwindSafe + 'static,
+            C: FnOnce() + 'static,
         {
             Self {
                 setup: Box::new(setup),
@@ -600,7 +676,7 @@ mod test {
         }
     }
 
-    pub(crate) fn run_test(test: Test) -> () {
+    pub(crate) fn run_test(test: Test) {
         (test.setup)();
 
         let result = std::panic::catch_unwind(test.test);
@@ -1,8 +1,113 @@
+use std::path::PathBuf;
+use wgsl_playground::HeadlessOptions;
+
+const USAGE: &str = "\
+Usage: wgsl_playground [--config PATH] [--headless [OPTIONS] | --bless [CASE...]
+                        | --import-shadertoy EXPORT [NAME] | --emit-generated FOLDER]
+
+--config reads the config from PATH instead of config.ron in the working directory, its assets
+folder being relative to the folder of the config either way.
+
+--bless renders the golden test cases, or only the given ones, as their new references.
+--import-shadertoy converts a Shadertoy JSON export to WGSL passes in the assets folder, under
+shadertoy/NAME, along with a config.ron running them.
+--emit-generated writes the WGSL generated from Rust, which shaders include from memory as
+generated/FILE, to FOLDER for editors and other tools. generated/params.wgsl is only written if
+the config is valid.
+
+Options of the headless mode:
+    --frames N..M     Frames to write, rendered at N / fps seconds (default: 0..1)
+    --size WxH        Resolution of the frames (default: window size of the config)
+    --fps FPS         Frames per second of the fixed timestep (default: 60)
+    --output FOLDER   Folder the PNGs are written to (default: frames)
+    --fallback        Render with the software adapter";
+
 fn main() {
-    let config: wgsl_playground::Config = ron::de::from_reader(std::io::BufReader::new(
-        std::fs::File::open("config.ron").expect("Config file should be available"),
-    ))
-    .expect("Config should be valid");
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    let config_path = match args.first().map(String::as_str) {
+        Some("--config") => {
+            let Some(path) = args.get(1).map(PathBuf::from) else {
+                eprintln!("Expected a config path\n\n{USAGE}");
+                std::process::exit(2);
+            };
+            args.drain(..2);
+            path
+        }
+        _ => PathBuf::from(wgsl_playground::CONFIG_PATH),
+    };
+    let config = wgsl_playground::read_config(&config_path);
+
+    if args.is_empty() {
+        // The window shows the error until the config is fixed
+        let (config, config_error) = match config {
+            Ok(config) => (config, None),
+            Err(diagnostic) => {
+                eprint!("{diagnostic}");
+                (
+                    wgsl_playground::Config::default().with_path(config_path),
+                    Some(diagnostic),
+                )
+            }
+        };
+        pollster::block_on(wgsl_playground::run(config, config_error));
+        return;
+    }
+
+    if args[0] == "--emit-generated" {
+        let (Some(folder), None) = (args.get(1), args.get(2)) else {
+            eprintln!("Expected a folder\n\n{USAGE}");
+            std::process::exit(2);
+        };
+        if let Err(diagnostic) = config.as_ref() {
+            eprint!("{diagnostic}");
+            eprintln!("Skipping params.wgsl, which is generated from the config");
+        }
+        if let Err(err) = wgsl_playground::emit_generated(folder.as_ref(), config.as_ref().ok()) {
+            eprintln!("Failed to write generated WGSL: {err:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let config = match config {
+        Ok(config) => config,
+        Err(diagnostic) => {
+            eprint!("{diagnostic}");
+            std::process::exit(1);
+        }
+    };
+
+    if args[0] == "--bless" {
+        if let Err(err) = pollster::block_on(wgsl_playground::bless_golden(&args[1..])) {
+            eprintln!("{err:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args[0] == "--import-shadertoy" {
+        let (Some(export), name, None) = (args.get(1), args.get(2), args.get(3)) else {
+            eprintln!("Expected a Shadertoy export and an optional name\n\n{USAGE}");
+            std::process::exit(2);
+        };
+        if let Err(err) =
+            wgsl_playground::import_shadertoy(&config, export.as_ref(), name.map(String::as_str))
+        {
+            eprintln!("{err:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    pollster::block_on(wgsl_playground::run(config));
+    let options = match HeadlessOptions::from_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = pollster::block_on(wgsl_playground::render_headless(&config, &options)) {
+        eprintln!("{err:#}");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,249 @@
+use crate::texture::Texture;
+
+use {
+    embedded_graphics::{
+        mono_font::{ascii::FONT_6X10, MonoTextStyle},
+        pixelcolor::{Rgb888, RgbColor},
+        prelude::*,
+        text::{Baseline, Text},
+    },
+    winit::dpi::PhysicalSize,
+};
+
+const PADDING: u32 = 8;
+const BACKGROUND: image::Rgba<u8> = image::Rgba([0, 0, 0, 200]);
+
+/// Text drawn on top of the final image, used to show compilation errors in the window.
+pub struct ErrorOverlay {
+    text: String,
+    size: PhysicalSize<u32>,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// Missing while there is no text to show
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl ErrorOverlay {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("fullscreen_texture.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Error Overlay Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Error Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            cache: None,
+            label: Some("Error Overlay Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                compilation_options: Default::default(),
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                compilation_options: Default::default(),
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            text: String::new(),
+            size,
+            pipeline,
+            bind_group_layout,
+            bind_group: None,
+        }
+    }
+
+    /// Replaces the displayed text, an empty text hides the overlay.
+    pub fn set_text(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, text: String) {
+        if text != self.text {
+            self.text = text;
+            self.rasterize(device, queue);
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: PhysicalSize<u32>) {
+        self.size = size;
+        self.rasterize(device, queue);
+    }
+
+    fn rasterize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let Some(image) = draw_text(&self.text, self.size) else {
+            self.bind_group = None;
+            return;
+        };
+
+        let texture = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(image),
+            Some("Error Overlay Texture"),
+        )
+        .expect("Overlay image should be a valid texture");
+
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Error Overlay Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+        }));
+    }
+
+    /// Draws the overlay on top of whatever `view` already contains.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let Some(bind_group) = self.bind_group.as_ref() else {
+            return;
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Error Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Draws `text` over a translucent background at the top of an otherwise transparent image.
+fn draw_text(text: &str, size: PhysicalSize<u32>) -> Option<image::RgbaImage> {
+    if text.is_empty() || size.width == 0 || size.height == 0 {
+        return None;
+    }
+
+    let mut image = image::RgbaImage::new(size.width, size.height);
+
+    let line_height = FONT_6X10.character_size.height;
+    let lines = text.lines().count() as u32;
+    let text_bottom = (PADDING * 2 + lines * line_height).min(size.height);
+    for y in 0..text_bottom {
+        for x in 0..size.width {
+            image.put_pixel(x, y, BACKGROUND);
+        }
+    }
+
+    let mut canvas = Canvas(&mut image);
+    for (i, line) in text.lines().enumerate() {
+        let color = if line.starts_with("error") {
+            Rgb888::new(255, 96, 96)
+        } else if line.starts_with("warning") {
+            Rgb888::new(255, 208, 96)
+        } else {
+            Rgb888::WHITE
+        };
+
+        // The font only covers ASCII, the diagnostics' box drawing gets approximated
+        let line = line
+            .chars()
+            .map(|c| match c {
+                '┌' | '└' => '+',
+                '─' => '-',
+                '│' => '|',
+                c if c.is_ascii() => c,
+                _ => '?',
+            })
+            .collect::<String>();
+
+        let position = Point::new(PADDING as i32, (PADDING + i as u32 * line_height) as i32);
+        Text::with_baseline(
+            &line,
+            position,
+            MonoTextStyle::new(&FONT_6X10, color),
+            Baseline::Top,
+        )
+        .draw(&mut canvas)
+        .expect("Drawing to an image should not fail");
+    }
+
+    Some(image)
+}
+
+/// Lets `embedded_graphics` draw text into an image.
+struct Canvas<'i>(&'i mut image::RgbaImage);
+
+impl OriginDimensions for Canvas<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.0.width(), self.0.height())
+    }
+}
+
+impl DrawTarget for Canvas<'_> {
+    type Color = Rgb888;
+    type Error = std::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (u32::try_from(point.x), u32::try_from(point.y))
+                && x < self.0.width()
+                && y < self.0.height()
+            {
+                self.0
+                    .put_pixel(x, y, image::Rgba([color.r(), color.g(), color.b(), 255]));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+use crate::{generated, shader_source::MemorySource};
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+/// A value shaders can read from `params`, adjustable while running.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ParamConfig {
+    /// Name of the field of `Params`, must be a valid WGSL identifier
+    pub name: String,
+    /// Initial value, which also sets the type of the parameter
+    pub default: ParamValue,
+    /// Bounds of every component, colors being kept between 0 and 1 by default
+    #[serde(default)]
+    pub min: Option<f32>,
+    #[serde(default)]
+    pub max: Option<f32>,
+    /// Change of a component per key press, defaults to a fiftieth of the range
+    #[serde(default)]
+    pub step: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum ParamValue {
+    F32(f32),
+    Vec2(f32, f32),
+    Vec3(f32, f32, f32),
+    Vec4(f32, f32, f32, f32),
+    /// Linear RGB, as a `vec3f`
+    Color(f32, f32, f32),
+    /// Stored as a `u32`, booleans can't be part of uniforms
+    Bool(bool),
+    Int(i32),
+}
+
+impl ParamValue {
+    /// Type of the value in WGSL, with its alignment and size in uniforms.
+    fn layout(&self) -> (&'static str, usize, usize) {
+        match self {
+            ParamValue::F32(_) => ("f32", 4, 4),
+            ParamValue::Vec2(..) => ("vec2f", 8, 8),
+            ParamValue::Vec3(..) | ParamValue::Color(..) => ("vec3f", 16, 12),
+            ParamValue::Vec4(..) => ("vec4f", 16, 16),
+            ParamValue::Bool(_) => ("u32", 4, 4),
+            ParamValue::Int(_) => ("i32", 4, 4),
+        }
+    }
+
+    fn components(&self) -> Vec<f32> {
+        match *self {
+            ParamValue::F32(x) => vec![x],
+            ParamValue::Vec2(x, y) => vec![x, y],
+            ParamValue::Vec3(x, y, z) | ParamValue::Color(x, y, z) => vec![x, y, z],
+            ParamValue::Vec4(x, y, z, w) => vec![x, y, z, w],
+            ParamValue::Bool(value) => vec![value as u32 as f32],
+            ParamValue::Int(value) => vec![value as f32],
+        }
+    }
+
+    fn set_component(&mut self, index: usize, value: f32) {
+        match self {
+            ParamValue::F32(x) => *x = value,
+            ParamValue::Vec2(x, y) => *[x, y][index] = value,
+            ParamValue::Vec3(x, y, z) | ParamValue::Color(x, y, z) => *[x, y, z][index] = value,
+            ParamValue::Vec4(x, y, z, w) => *[x, y, z, w][index] = value,
+            ParamValue::Bool(flag) => *flag = value != 0.,
+            ParamValue::Int(int) => *int = value.round() as i32,
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match *self {
+            ParamValue::Bool(value) => (value as u32).to_le_bytes().to_vec(),
+            ParamValue::Int(value) => value.to_le_bytes().to_vec(),
+            _ => bytemuck::cast_slice(&self.components()).to_vec(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamValue::Bool(value) => write!(f, "{value}"),
+            ParamValue::Int(value) => write!(f, "{value}"),
+            ParamValue::F32(value) => write!(f, "{value}"),
+            _ => {
+                let components = self.components();
+                write!(f, "(")?;
+                for (i, component) in components.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{component}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Current values of the params of the config, packed into a uniform as `Params`.
+pub struct Params {
+    configs: Vec<ParamConfig>,
+    values: Vec<ParamValue>,
+    /// Index of the selected component, counting the components of every param in order
+    selected: usize,
+}
+
+impl Params {
+    /// Fails if two params share a name or one isn't a valid identifier.
+    pub fn new(configs: &[ParamConfig]) -> Result<Self, String> {
+        let mut names = HashSet::new();
+        for config in configs {
+            let valid = config
+                .name
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && config
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(format!("Invalid param name `{}`", config.name));
+            }
+            if !names.insert(config.name.as_str()) {
+                return Err(format!("Param `{}` is defined twice", config.name));
+            }
+        }
+
+        let mut params = Self {
+            configs: configs.to_vec(),
+            values: configs.iter().map(|config| config.default).collect(),
+            selected: 0,
+        };
+        for index in 0..params.values.len() {
+            params.clamp(index);
+        }
+        Ok(params)
+    }
+
+    /// Keeps the values of `previous` for the params that still have the same name and type.
+    pub fn carry_over(&mut self, previous: &Params) {
+        for index in 0..self.values.len() {
+            if let Some(value) = previous.get(&self.configs[index].name)
+                && std::mem::discriminant(&value) == std::mem::discriminant(&self.values[index])
+            {
+                self.values[index] = value;
+                self.clamp(index);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<ParamValue> {
+        let index = self.configs.iter().position(|config| config.name == name)?;
+        Some(self.values[index])
+    }
+
+    /// Offset of every param in the uniform, and the size of the whole struct.
+    fn layout(&self) -> (Vec<usize>, usize) {
+        let mut offsets = Vec::new();
+        let mut end = 0usize;
+        let mut struct_align = 16;
+        for value in self.values.iter() {
+            let (_, align, size) = value.layout();
+            let offset = end.next_multiple_of(align);
+            offsets.push(offset);
+            end = offset + size;
+            struct_align = struct_align.max(align);
+        }
+
+        (
+            offsets,
+            end.next_multiple_of(struct_align).max(struct_align),
+        )
+    }
+
+    /// Content of the uniform buffer.
+    pub fn bytes(&self) -> Vec<u8> {
+        let (offsets, size) = self.layout();
+        let mut bytes = vec![0; size];
+        for (value, offset) in self.values.iter().zip(offsets) {
+            let value = value.bytes();
+            bytes[offset..offset + value.len()].copy_from_slice(&value);
+        }
+        bytes
+    }
+
+    /// Definition of `Params` for shaders, included from memory as `generated/params.wgsl`.
+    pub fn wgsl(&self) -> String {
+        let mut wgsl = String::from(
+            "// Generated from the params of the config, bind it with:\n\
+             //     @group(G) @binding(B) var<uniform> params: Params;\n\
+             // Booleans are stored as `u32`.\n\
+             struct Params {\n",
+        );
+        for (config, value) in self.configs.iter().zip(self.values.iter()) {
+            wgsl += &format!("    {}: {},\n", config.name, value.layout().0);
+        }
+        wgsl + "}\n"
+    }
+
+    /// Source of `generated/params.wgsl`, which is missing if there are no params.
+    pub fn source(&self) -> MemorySource {
+        match self.is_empty() {
+            true => MemorySource::new(),
+            false => generated::runtime_source("params.wgsl", &self.wgsl()),
+        }
+    }
+
+    /// Param and component of the selection.
+    fn selection(&self) -> Option<(usize, usize)> {
+        let mut remaining = self.selected;
+        for (index, value) in self.values.iter().enumerate() {
+            let components = value.components().len();
+            if remaining < components {
+                return Some((index, remaining));
+            }
+            remaining -= components;
+        }
+        None
+    }
+
+    /// Moves the selection by `offset` components, wrapping around.
+    pub fn select(&mut self, offset: i32) {
+        let count = self
+            .values
+            .iter()
+            .map(|value| value.components().len())
+            .sum::<usize>();
+        if count > 0 {
+            self.selected =
+                (self.selected as i64 + offset as i64).rem_euclid(count as i64) as usize;
+        }
+    }
+
+    /// Changes the selected component by `steps` steps, a step toggling booleans.
+    pub fn adjust(&mut self, steps: i32) {
+        let Some((index, component)) = self.selection() else {
+            return;
+        };
+
+        let config = &self.configs[index];
+        let value = &mut self.values[index];
+        let current = value.components()[component];
+        let step = config
+            .step
+            .unwrap_or_else(|| match (*value, Self::range(config)) {
+                (ParamValue::Int(_), _) => 1.,
+                (_, (Some(min), Some(max))) => (max - min) / 50.,
+                _ => 0.01,
+            });
+        let new = match value {
+            ParamValue::Bool(flag) => (!*flag) as u32 as f32,
+            _ => current + steps as f32 * step,
+        };
+        value.set_component(component, new);
+        self.clamp(index);
+    }
+
+    /// Bounds of the components of a param.
+    fn range(config: &ParamConfig) -> (Option<f32>, Option<f32>) {
+        match config.default {
+            ParamValue::Color(..) => (config.min.or(Some(0.)), config.max.or(Some(1.))),
+            _ => (config.min, config.max),
+        }
+    }
+
+    fn clamp(&mut self, index: usize) {
+        let (min, max) = Self::range(&self.configs[index]);
+        let value = &mut self.values[index];
+
+        for (component, current) in value.components().into_iter().enumerate() {
+            let clamped = current
+                .max(min.unwrap_or(f32::NEG_INFINITY))
+                .min(max.unwrap_or(f32::INFINITY));
+            value.set_component(component, clamped);
+        }
+    }
+}
+
+impl std::fmt::Display for Params {
+    /// Prints the selected param, e.g. `param 2/3: tint = (1, 0.5, 0.2) [g]`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some((index, component)) = self.selection() else {
+            return write!(f, "no params");
+        };
+
+        let value = &self.values[index];
+        write!(
+            f,
+            "param {}/{}: {} = {value}",
+            index + 1,
+            self.values.len(),
+            self.configs[index].name
+        )?;
+        if value.components().len() > 1 {
+            let names = match value {
+                ParamValue::Color(..) => ["r", "g", "b", "a"],
+                _ => ["x", "y", "z", "w"],
+            };
+            write!(f, " [{}]", names[component])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    #[test]
+    fn params() {
+        run_test(Test::new(
+            || {},
+            || {
+                let configs: Vec<ParamConfig> = ron::from_str(
+                    "[
+                        (name: \"distortion\", default: F32(0.0025), min: Some(0.), max: Some(0.01)),
+                        (name: \"tint\", default: Color(1., 0.5, 0.2), step: Some(0.25)),
+                        (name: \"octaves\", default: Int(5), min: Some(1), max: Some(8)),
+                        (name: \"lines\", default: Bool(true)),
+                        (name: \"offset\", default: Vec2(0., 0.), step: Some(0.25)),
+                    ]",
+                )
+                .expect("Params should parse");
+                let mut params = Params::new(&configs).expect("Params should be valid");
+
+                // Offsets match the ones of naga
+                let module = naga::front::wgsl::parse_str(&params.wgsl())
+                    .expect("Params should be valid WGSL");
+                let (_, ty) = module
+                    .types
+                    .iter()
+                    .find(|(_, ty)| ty.name.as_deref() == Some("Params"))
+                    .expect("Params should be defined");
+                let naga::TypeInner::Struct { members, span } = &ty.inner else {
+                    panic!("Params should be a struct");
+                };
+                let (offsets, size) = params.layout();
+                assert_eq!(
+                    members
+                        .iter()
+                        .map(|member| member.offset as usize)
+                        .collect::<Vec<_>>(),
+                    offsets
+                );
+                assert_eq!(size, (*span as usize).next_multiple_of(16));
+                assert_eq!(params.bytes().len(), size);
+
+                // Steps are clamped, booleans toggle, colors stay in 0..1
+                params.adjust(100);
+                assert_eq!(params.get("distortion"), Some(ParamValue::F32(0.01)));
+                params.select(1);
+                params.adjust(1);
+                assert_eq!(params.get("tint"), Some(ParamValue::Color(1., 0.5, 0.2)));
+                params.select(1);
+                params.adjust(-1);
+                assert_eq!(params.get("tint"), Some(ParamValue::Color(1., 0.25, 0.2)));
+                assert_eq!(params.to_string(), "param 2/5: tint = (1, 0.25, 0.2) [g]");
+                params.select(2);
+                params.adjust(5);
+                assert_eq!(params.get("octaves"), Some(ParamValue::Int(8)));
+                params.select(1);
+                params.adjust(1);
+                assert_eq!(params.get("lines"), Some(ParamValue::Bool(false)));
+                params.select(2);
+                params.adjust(-2);
+                assert_eq!(params.get("offset"), Some(ParamValue::Vec2(0., -0.5)));
+                params.select(1);
+                assert_eq!(params.to_string(), "param 1/5: distortion = 0.01");
+
+                let duplicate = [configs[0].clone(), configs[0].clone()];
+                assert!(Params::new(&duplicate).is_err());
+
+                // Adjusted values survive config changes unless the type changes
+                let mut changed = configs.clone();
+                changed[0].max = Some(0.005);
+                changed[1].default = ParamValue::Vec3(0., 0., 0.);
+                changed.remove(4);
+                let mut reloaded = Params::new(&changed).expect("Params should be valid");
+                reloaded.carry_over(&params);
+                assert_eq!(reloaded.get("distortion"), Some(ParamValue::F32(0.005)));
+                assert_eq!(reloaded.get("tint"), Some(ParamValue::Vec3(0., 0., 0.)));
+                assert_eq!(reloaded.get("octaves"), Some(ParamValue::Int(8)));
+                assert_eq!(reloaded.get("offset"), None);
+            },
+            || {},
+        ));
+    }
+}
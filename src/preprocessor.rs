@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+/// Preprocessor symbols and their (possibly empty) values.
+pub type Defines = HashMap<String, String>;
+
+/// Nested `if`/`ifdef`/`ifndef` blocks of a single file.
+#[derive(Default)]
+pub struct Conditions {
+    stack: Vec<Branch>,
+}
+
+struct Branch {
+    /// Whether lines of the current branch end up in the output
+    active: bool,
+    /// Whether the enclosing block is itself active
+    parent_active: bool,
+    /// Whether a branch of this block was already selected
+    taken: bool,
+    has_else: bool,
+}
+
+impl Conditions {
+    pub fn is_active(&self) -> bool {
+        self.stack.last().is_none_or(|branch| branch.active)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    pub fn push(&mut self, condition: bool) {
+        let parent_active = self.is_active();
+        self.stack.push(Branch {
+            active: parent_active && condition,
+            parent_active,
+            taken: condition,
+            has_else: false,
+        });
+    }
+
+    pub fn flip(&mut self) -> Result<(), &'static str> {
+        let Some(branch) = self.stack.last_mut() else {
+            return Err("`else` without matching `if`");
+        };
+
+        if branch.has_else {
+            return Err("`else` was already used in this block");
+        }
+
+        branch.has_else = true;
+        branch.active = branch.parent_active && !branch.taken;
+        branch.taken = true;
+
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<(), &'static str> {
+        self.stack
+            .pop()
+            .map(|_| ())
+            .ok_or("`endif` without matching `if`")
+    }
+}
+
+/// Evaluates the expression of an `//% if` directive, C preprocessor style.
+///
+/// Symbols evaluate to their integer value, `1` if they were defined without one and `0` if they
+/// are not defined at all. `defined(SYMBOL)` checks for a symbol's existence. Operators have C
+/// precedence, from loosest to tightest: `||`, `&&`, `|`, `&`, equality, comparison, additive,
+/// multiplicative and unary.
+pub fn evaluate(expr: &str, defines: &Defines) -> Result<i64, String> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser {
+        tokens: tokens.as_slice(),
+        defines,
+    };
+
+    let value = parser.or()?;
+    match parser.tokens.first() {
+        None => Ok(value),
+        Some(token) => Err(format!("Unexpected token `{token}` in expression")),
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<&str>, String> {
+    const OPERATORS: [&str; 18] = [
+        "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", "+", "-", "*", "/", "%", "&",
+        "|",
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_alphanumeric() || c == '_' {
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            op.len()
+        } else {
+            return Err(format!("Unexpected character `{c}` in expression"));
+        };
+
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'t, 'd> {
+    tokens: &'t [&'t str],
+    defines: &'d Defines,
+}
+
+impl<'t> Parser<'t, '_> {
+    fn peek(&self) -> Option<&'t str> {
+        self.tokens.first().copied()
+    }
+
+    fn next(&mut self) -> Result<&'t str, String> {
+        let (first, rest) = self
+            .tokens
+            .split_first()
+            .ok_or("Unexpected end of expression")?;
+        self.tokens = rest;
+        Ok(first)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("Expected `{expected}`, found `{token}`")),
+        }
+    }
+
+    /// Parses a left-associative chain of the given operators.
+    fn binary(
+        &mut self,
+        operators: &[&str],
+        operand: fn(&mut Self) -> Result<i64, String>,
+        apply: fn(&str, i64, i64) -> Result<i64, String>,
+    ) -> Result<i64, String> {
+        let mut lhs = operand(self)?;
+        while let Some(op) = self.peek().filter(|token| operators.contains(token)) {
+            self.next()?;
+            let rhs = operand(self)?;
+            lhs = apply(op, lhs, rhs)?;
+        }
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<i64, String> {
+        self.binary(&["||"], Self::and, |_, lhs, rhs| {
+            Ok((lhs != 0 || rhs != 0) as i64)
+        })
+    }
+
+    fn and(&mut self) -> Result<i64, String> {
+        self.binary(&["&&"], Self::bit_or, |_, lhs, rhs| {
+            Ok((lhs != 0 && rhs != 0) as i64)
+        })
+    }
+
+    fn bit_or(&mut self) -> Result<i64, String> {
+        self.binary(&["|"], Self::bit_and, |_, lhs, rhs| Ok(lhs | rhs))
+    }
+
+    fn bit_and(&mut self) -> Result<i64, String> {
+        self.binary(&["&"], Self::equality, |_, lhs, rhs| Ok(lhs & rhs))
+    }
+
+    fn equality(&mut self) -> Result<i64, String> {
+        self.binary(&["==", "!="], Self::comparison, |op, lhs, rhs| {
+            Ok(match op {
+                "==" => lhs == rhs,
+                _ => lhs != rhs,
+            } as i64)
+        })
+    }
+
+    fn comparison(&mut self) -> Result<i64, String> {
+        self.binary(&["<", "<=", ">", ">="], Self::additive, |op, lhs, rhs| {
+            Ok(match op {
+                "<" => lhs < rhs,
+                "<=" => lhs <= rhs,
+                ">" => lhs > rhs,
+                _ => lhs >= rhs,
+            } as i64)
+        })
+    }
+
+    fn additive(&mut self) -> Result<i64, String> {
+        self.binary(&["+", "-"], Self::multiplicative, |op, lhs, rhs| {
+            Ok(match op {
+                "+" => lhs.wrapping_add(rhs),
+                _ => lhs.wrapping_sub(rhs),
+            })
+        })
+    }
+
+    fn multiplicative(&mut self) -> Result<i64, String> {
+        self.binary(&["*", "/", "%"], Self::unary, |op, lhs, rhs| match op {
+            "*" => Ok(lhs.wrapping_mul(rhs)),
+            _ if rhs == 0 => Err("Division by zero in expression".to_owned()),
+            "/" => Ok(lhs.wrapping_div(rhs)),
+            _ => Ok(lhs.wrapping_rem(rhs)),
+        })
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        match self.peek() {
+            Some("!") => {
+                self.next()?;
+                Ok((self.unary()? == 0) as i64)
+            }
+            Some("-") => {
+                self.next()?;
+                Ok(self.unary()?.wrapping_neg())
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64, String> {
+        match self.next()? {
+            "(" => {
+                let value = self.or()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            "defined" => {
+                let parenthesized = self.peek() == Some("(");
+                if parenthesized {
+                    self.next()?;
+                }
+                let symbol = self.next()?;
+                if parenthesized {
+                    self.expect(")")?;
+                }
+                Ok(self.defines.contains_key(symbol) as i64)
+            }
+            token if token.starts_with(|c: char| c.is_ascii_digit()) => token
+                .parse()
+                .map_err(|_| format!("Invalid integer literal `{token}`")),
+            token if token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') => {
+                self.symbol(token)
+            }
+            token => Err(format!("Unexpected token `{token}` in expression")),
+        }
+    }
+
+    fn symbol(&self, symbol: &str) -> Result<i64, String> {
+        let Some(value) = self.defines.get(symbol).map(|value| value.trim()) else {
+            return Ok(0);
+        };
+
+        match value {
+            "" | "true" => Ok(1),
+            "false" => Ok(0),
+            _ => value
+                .parse()
+                .map_err(|_| format!("Value of `{symbol}` is not an integer: `{value}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    #[test]
+    fn preprocessor_expressions() {
+        run_test(Test::new(
+            || {},
+            || {
+                let defines = Defines::from([
+                    ("QUALITY".to_owned(), "2".to_owned()),
+                    ("FANCY".to_owned(), String::new()),
+                    ("NAME".to_owned(), "perlin".to_owned()),
+                ]);
+                let eval = |expr| evaluate(expr, &defines);
+
+                assert_eq!(eval("QUALITY >= 2"), Ok(1));
+                assert_eq!(eval("QUALITY > 2 || defined(FANCY)"), Ok(1));
+                assert_eq!(eval("!defined MISSING && MISSING == 0"), Ok(1));
+                assert_eq!(eval("(1 + 2) * -3 % 4"), Ok(-1));
+                assert_eq!(eval("4 | 2 & 1"), Ok(4));
+                assert_eq!(eval("QUALITY & 2 == 2"), Ok(0));
+                assert_eq!(eval("(QUALITY & 2) == 2"), Ok(1));
+                assert!(eval("NAME").is_err());
+                assert!(eval("QUALITY /").is_err());
+                assert!(eval("1 / 0").is_err());
+                assert!(eval("(1").is_err());
+
+                let mut conditions = Conditions::default();
+                conditions.push(true);
+                conditions.push(false);
+                assert!(!conditions.is_active());
+                assert_eq!(conditions.flip(), Ok(()));
+                assert!(conditions.is_active());
+                assert!(conditions.flip().is_err());
+                assert_eq!(conditions.pop(), Ok(()));
+                assert_eq!(conditions.flip(), Ok(()));
+                assert!(!conditions.is_active());
+                assert_eq!(conditions.pop(), Ok(()));
+                assert!(conditions.is_closed());
+                assert!(conditions.pop().is_err());
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,722 @@
+use crate::texture::Texture;
+
+use std::collections::BTreeMap;
+
+/// Kind of resource a global of the shader has to be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Uniform,
+    Storage {
+        read_only: bool,
+    },
+    Texture {
+        sample_type: wgpu::TextureSampleType,
+        view_dimension: wgpu::TextureViewDimension,
+        multisampled: bool,
+    },
+    StorageTexture {
+        access: wgpu::StorageTextureAccess,
+        format: wgpu::TextureFormat,
+        view_dimension: wgpu::TextureViewDimension,
+    },
+    Sampler {
+        comparison: bool,
+    },
+}
+
+impl BindingKind {
+    /// Layout entry type of the binding, buffers being at least `size` bytes.
+    fn binding_type(&self, size: u64) -> wgpu::BindingType {
+        match *self {
+            BindingKind::Uniform => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(size),
+            },
+            BindingKind::Storage { read_only } => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(size),
+            },
+            BindingKind::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            } => wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            },
+            BindingKind::StorageTexture {
+                access,
+                format,
+                view_dimension,
+            } => wgpu::BindingType::StorageTexture {
+                access,
+                format,
+                view_dimension,
+            },
+            BindingKind::Sampler { comparison: true } => {
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison)
+            }
+            BindingKind::Sampler { comparison: false } => {
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+            }
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            BindingKind::Uniform => "uniform buffer",
+            BindingKind::Storage { .. } => "storage buffer",
+            BindingKind::Texture { .. } => "texture",
+            BindingKind::StorageTexture { .. } => "storage texture",
+            BindingKind::Sampler { .. } => "sampler",
+        }
+    }
+}
+
+/// A resource global of a shader module, e.g. `@group(1) @binding(0) var<uniform> time: f32;`.
+#[derive(Debug, Clone)]
+pub struct ShaderBinding {
+    pub name: String,
+    pub group: u32,
+    pub binding: u32,
+    pub kind: BindingKind,
+    /// Size of the declared type, a runtime-sized array counting as a single element
+    pub size: u64,
+    /// Stages of the entry points using the global
+    pub visibility: wgpu::ShaderStages,
+    /// Span of the global's declaration in the finished shader
+    pub span: naga::Span,
+}
+
+/// Something wrong with the resources a shader declares.
+#[derive(Debug, Clone)]
+pub struct BindingError {
+    pub message: String,
+    pub span: naga::Span,
+    pub note: Option<String>,
+}
+
+impl BindingError {
+    fn new(message: impl ToString, span: naga::Span) -> Self {
+        Self {
+            message: message.to_string(),
+            span,
+            note: None,
+        }
+    }
+
+    fn with_note(mut self, note: impl ToString) -> Self {
+        self.note = Some(note.to_string());
+        self
+    }
+}
+
+/// Lists the resource bindings declared by `module`, sorted by group and binding.
+pub fn reflect(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> Result<Vec<ShaderBinding>, Vec<BindingError>> {
+    let mut bindings = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut layouter = naga::proc::Layouter::default();
+    if let Err(error) = layouter.update(module.to_ctx()) {
+        return Err(vec![BindingError::new(
+            format!("Cannot lay out the shader's types: {error}"),
+            naga::Span::default(),
+        )]);
+    }
+
+    for (handle, global) in module.global_variables.iter() {
+        let Some(resource_binding) = global.binding.as_ref() else {
+            continue;
+        };
+
+        // Writable storage can't be visible to vertex shaders, even if they don't use it
+        let visibility = module
+            .entry_points
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !info.get_entry_point(*i)[handle].is_empty())
+            .fold(wgpu::ShaderStages::NONE, |visibility, (_, entry_point)| {
+                visibility
+                    | match entry_point.stage {
+                        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+                    }
+            });
+
+        let span = module.global_variables.get_span(handle);
+        let name = global.name.clone().unwrap_or_default();
+        match binding_kind(module, global) {
+            Ok(kind) => bindings.push(ShaderBinding {
+                name,
+                group: resource_binding.group,
+                binding: resource_binding.binding,
+                kind,
+                size: layouter[global.ty].size as u64,
+                visibility,
+                span,
+            }),
+            Err(message) => errors.push(BindingError::new(
+                format!("Cannot bind `{name}`: {message}"),
+                span,
+            )),
+        }
+    }
+
+    bindings.sort_by_key(|binding| (binding.group, binding.binding));
+    for pair in bindings.windows(2) {
+        if (pair[0].group, pair[0].binding) == (pair[1].group, pair[1].binding) {
+            errors.push(
+                BindingError::new(
+                    format!(
+                        "`{}` and `{}` share @group({}) @binding({})",
+                        pair[0].name, pair[1].name, pair[1].group, pair[1].binding
+                    ),
+                    pair[1].span,
+                )
+                .with_note(format!("`{}` is declared first", pair[0].name)),
+            );
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(bindings)
+    } else {
+        Err(errors)
+    }
+}
+
+fn binding_kind(
+    module: &naga::Module,
+    global: &naga::GlobalVariable,
+) -> Result<BindingKind, String> {
+    match global.space {
+        naga::AddressSpace::Uniform => return Ok(BindingKind::Uniform),
+        naga::AddressSpace::Storage { access } => {
+            return Ok(BindingKind::Storage {
+                read_only: !access.contains(naga::StorageAccess::STORE),
+            });
+        }
+        naga::AddressSpace::Handle => {}
+        space => return Err(format!("unsupported address space {space:?}")),
+    }
+
+    match module.types[global.ty].inner {
+        naga::TypeInner::Sampler { comparison } => Ok(BindingKind::Sampler { comparison }),
+        naga::TypeInner::Image {
+            dim,
+            arrayed,
+            class,
+        } => {
+            let view_dimension = match (dim, arrayed) {
+                (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+                (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+                (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+                (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+                (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+                (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+            };
+
+            Ok(match class {
+                naga::ImageClass::Sampled { kind, multi } => BindingKind::Texture {
+                    sample_type: match kind {
+                        naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                        naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                        _ => wgpu::TextureSampleType::Float { filterable: !multi },
+                    },
+                    view_dimension,
+                    multisampled: multi,
+                },
+                naga::ImageClass::Depth { multi } => BindingKind::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension,
+                    multisampled: multi,
+                },
+                naga::ImageClass::Storage { format, access } => BindingKind::StorageTexture {
+                    access: match (
+                        access.contains(naga::StorageAccess::LOAD),
+                        access.contains(naga::StorageAccess::STORE),
+                    ) {
+                        (true, true) => wgpu::StorageTextureAccess::ReadWrite,
+                        (true, false) => wgpu::StorageTextureAccess::ReadOnly,
+                        _ => wgpu::StorageTextureAccess::WriteOnly,
+                    },
+                    format: storage_format(format),
+                    view_dimension,
+                },
+            })
+        }
+        naga::TypeInner::BindingArray { .. } => Err("binding arrays are not supported".to_owned()),
+        ref inner => Err(format!("unexpected handle type {inner:?}")),
+    }
+}
+
+fn storage_format(format: naga::StorageFormat) -> wgpu::TextureFormat {
+    use {naga::StorageFormat as Sf, wgpu::TextureFormat as Tf};
+
+    match format {
+        Sf::R8Unorm => Tf::R8Unorm,
+        Sf::R8Snorm => Tf::R8Snorm,
+        Sf::R8Uint => Tf::R8Uint,
+        Sf::R8Sint => Tf::R8Sint,
+        Sf::R16Uint => Tf::R16Uint,
+        Sf::R16Sint => Tf::R16Sint,
+        Sf::R16Float => Tf::R16Float,
+        Sf::Rg8Unorm => Tf::Rg8Unorm,
+        Sf::Rg8Snorm => Tf::Rg8Snorm,
+        Sf::Rg8Uint => Tf::Rg8Uint,
+        Sf::Rg8Sint => Tf::Rg8Sint,
+        Sf::R32Uint => Tf::R32Uint,
+        Sf::R32Sint => Tf::R32Sint,
+        Sf::R32Float => Tf::R32Float,
+        Sf::Rg16Uint => Tf::Rg16Uint,
+        Sf::Rg16Sint => Tf::Rg16Sint,
+        Sf::Rg16Float => Tf::Rg16Float,
+        Sf::Rgba8Unorm => Tf::Rgba8Unorm,
+        Sf::Rgba8Snorm => Tf::Rgba8Snorm,
+        Sf::Rgba8Uint => Tf::Rgba8Uint,
+        Sf::Rgba8Sint => Tf::Rgba8Sint,
+        Sf::Bgra8Unorm => Tf::Bgra8Unorm,
+        Sf::Rgb10a2Uint => Tf::Rgb10a2Uint,
+        Sf::Rgb10a2Unorm => Tf::Rgb10a2Unorm,
+        Sf::Rg11b10Float => Tf::Rg11b10Float,
+        Sf::Rg32Uint => Tf::Rg32Uint,
+        Sf::Rg32Sint => Tf::Rg32Sint,
+        Sf::Rg32Float => Tf::Rg32Float,
+        Sf::Rgba16Uint => Tf::Rgba16Uint,
+        Sf::Rgba16Sint => Tf::Rgba16Sint,
+        Sf::Rgba16Float => Tf::Rgba16Float,
+        Sf::Rgba32Uint => Tf::Rgba32Uint,
+        Sf::Rgba32Sint => Tf::Rgba32Sint,
+        Sf::Rgba32Float => Tf::Rgba32Float,
+        Sf::R16Unorm => Tf::R16Unorm,
+        Sf::R16Snorm => Tf::R16Snorm,
+        Sf::Rg16Unorm => Tf::Rg16Unorm,
+        Sf::Rg16Snorm => Tf::Rg16Snorm,
+        Sf::Rgba16Unorm => Tf::Rgba16Unorm,
+        Sf::Rgba16Snorm => Tf::Rgba16Snorm,
+    }
+}
+
+/// A GPU resource shaders can bind by name.
+#[derive(Clone, Copy)]
+pub enum Resource<'r> {
+    Buffer(&'r wgpu::Buffer),
+    Texture(&'r Texture),
+    Sampler(&'r wgpu::Sampler),
+}
+
+impl<'r> Resource<'r> {
+    fn binding_resource(&self) -> wgpu::BindingResource<'r> {
+        match *self {
+            Resource::Buffer(buffer) => buffer.as_entire_binding(),
+            Resource::Texture(texture) => wgpu::BindingResource::TextureView(&texture.view),
+            Resource::Sampler(sampler) => wgpu::BindingResource::Sampler(sampler),
+        }
+    }
+
+    /// Checks that the resource can be bound to `binding`.
+    fn check(&self, binding: &ShaderBinding) -> Result<(), String> {
+        match (self, &binding.kind) {
+            (Resource::Buffer(buffer), BindingKind::Uniform)
+                if !buffer.usage().contains(wgpu::BufferUsages::UNIFORM) =>
+            {
+                Err("it is not usable as a uniform buffer".to_owned())
+            }
+            (Resource::Buffer(buffer), BindingKind::Storage { .. })
+                if !buffer.usage().contains(wgpu::BufferUsages::STORAGE) =>
+            {
+                Err("it is not usable as a storage buffer".to_owned())
+            }
+            (Resource::Buffer(buffer), BindingKind::Uniform | BindingKind::Storage { .. })
+                if buffer.size() < binding.size =>
+            {
+                Err(format!(
+                    "it is {} bytes but the shader expects at least {}",
+                    buffer.size(),
+                    binding.size
+                ))
+            }
+            (Resource::Texture(texture), BindingKind::Texture { sample_type, .. }) => {
+                let format = texture.texture.format();
+                if !texture
+                    .texture
+                    .usage()
+                    .contains(wgpu::TextureUsages::TEXTURE_BINDING)
+                {
+                    Err("it can't be sampled".to_owned())
+                } else if format
+                    .sample_type(None, None)
+                    .as_ref()
+                    .map(std::mem::discriminant)
+                    != Some(std::mem::discriminant(sample_type))
+                {
+                    Err(format!(
+                        "its format {format:?} doesn't match the texture type"
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            (Resource::Texture(texture), BindingKind::StorageTexture { format, .. }) => {
+                if !texture
+                    .texture
+                    .usage()
+                    .contains(wgpu::TextureUsages::STORAGE_BINDING)
+                {
+                    Err("it is not usable as a storage texture".to_owned())
+                } else if texture.texture.format() != *format {
+                    Err(format!(
+                        "its format is {:?} but is declared as {format:?}",
+                        texture.texture.format()
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            (Resource::Buffer(_), BindingKind::Uniform | BindingKind::Storage { .. })
+            | (Resource::Sampler(_), BindingKind::Sampler { .. }) => Ok(()),
+            (resource, kind) => Err(format!(
+                "it is a {} but is declared as a {}",
+                resource.description(),
+                kind.description()
+            )),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Resource::Buffer(_) => "buffer",
+            Resource::Texture(_) => "texture",
+            Resource::Sampler(_) => "sampler",
+        }
+    }
+}
+
+/// Named resources shaders get bound to, by declaring a global of the same name.
+#[derive(Default)]
+pub struct ResourceRegistry<'r> {
+    resources: BTreeMap<String, Resource<'r>>,
+}
+
+impl<'r> ResourceRegistry<'r> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, resource: Resource<'r>) -> Self {
+        self.resources.insert(name.into(), resource);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Resource<'r>> {
+        self.resources.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.resources.keys().map(String::as_str)
+    }
+}
+
+/// Pipeline layout of a shader, built from the resources it declares.
+pub struct BindingLayout {
+    bindings: Vec<ShaderBinding>,
+    bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+    pub pipeline_layout: wgpu::PipelineLayout,
+}
+
+impl BindingLayout {
+    /// Reflects the bindings of `module` and checks that `registry` can provide all of them.
+    pub fn new(
+        device: &wgpu::Device,
+        module: &naga::Module,
+        info: &naga::valid::ModuleInfo,
+        registry: &ResourceRegistry,
+        label: &str,
+    ) -> Result<Self, Vec<BindingError>> {
+        let bindings = reflect(module, info)?;
+
+        let max_bind_groups = device.limits().max_bind_groups;
+        let errors = bindings
+            .iter()
+            .filter_map(|binding| {
+                if binding.group >= max_bind_groups {
+                    return Some(BindingError::new(
+                        format!(
+                            "`{}` uses @group({}) but the device only supports {max_bind_groups} groups",
+                            binding.name, binding.group
+                        ),
+                        binding.span,
+                    ));
+                }
+
+                let Some(resource) = registry.get(&binding.name) else {
+                    return Some(
+                        BindingError::new(format!("Unknown resource `{}`", binding.name), binding.span)
+                            .with_note(format!(
+                                "available resources are {}",
+                                registry
+                                    .names()
+                                    .map(|name| format!("`{name}`"))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )),
+                    );
+                };
+
+                resource.check(binding).err().map(|message| {
+                    BindingError::new(format!("Cannot bind `{}`: {message}", binding.name), binding.span)
+                })
+            })
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Groups the shader skips still need an (empty) layout
+        let group_count = bindings.last().map_or(0, |binding| binding.group + 1);
+        let bind_group_layouts = (0..group_count)
+            .map(|group| {
+                let entries = bindings
+                    .iter()
+                    .filter(|binding| binding.group == group)
+                    .map(|binding| wgpu::BindGroupLayoutEntry {
+                        binding: binding.binding,
+                        visibility: binding.visibility,
+                        ty: binding.kind.binding_type(binding.size),
+                        count: None,
+                    })
+                    .collect::<Vec<_>>();
+
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&format!("{label} Bind Group Layout {group}")),
+                    entries: &entries,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{label} Layout")),
+            bind_group_layouts: &bind_group_layouts.iter().collect::<Vec<_>>(),
+            push_constant_ranges: &[],
+        });
+
+        Ok(Self {
+            bindings,
+            bind_group_layouts,
+            pipeline_layout,
+        })
+    }
+
+    /// Creates one bind group per group of the layout, `None` if a resource is missing from
+    /// `registry` or can't be bound.
+    pub fn bind_groups(
+        &self,
+        device: &wgpu::Device,
+        registry: &ResourceRegistry,
+    ) -> Option<Vec<wgpu::BindGroup>> {
+        self.bind_group_layouts
+            .iter()
+            .enumerate()
+            .map(|(group, layout)| {
+                let entries = self
+                    .bindings
+                    .iter()
+                    .filter(|binding| binding.group == group as u32)
+                    .map(|binding| {
+                        registry
+                            .get(&binding.name)
+                            .filter(|resource| resource.check(binding).is_ok())
+                            .map(|resource| wgpu::BindGroupEntry {
+                                binding: binding.binding,
+                                resource: resource.binding_resource(),
+                            })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout,
+                    entries: &entries,
+                }))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+    use indoc::indoc;
+
+    fn validate(module: &naga::Module) -> naga::valid::ModuleInfo {
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(module)
+        .expect("Test shader should be valid")
+    }
+
+    #[test]
+    fn reflect_bindings() {
+        run_test(Test::new(
+            || {},
+            || {
+                let module = naga::front::wgsl::parse_str(indoc! {"
+                    @group(2) @binding(1)
+                    var<uniform> time: f32;
+                    @group(0) @binding(3)
+                    var<storage, read_write> particles: array<vec4f>;
+                    @group(0) @binding(0)
+                    var t_diffuse: texture_2d<f32>;
+                    @group(0) @binding(1)
+                    var s_diffuse: sampler;
+                    @group(1) @binding(0)
+                    var output: texture_storage_2d<rgba16float, write>;
+                    var<private> not_a_resource: f32;
+
+                    @compute @workgroup_size(1)
+                    fn cs_main() {
+                        textureStore(output, vec2u(0), textureLoad(t_diffuse, vec2u(0), 0));
+                        particles[0] = vec4f(time);
+                    }
+
+                    @fragment
+                    fn fs_main() -> @location(0) vec4f {
+                        return textureSample(t_diffuse, s_diffuse, vec2f(0)) * time;
+                    }
+                "})
+                .expect("Test shader should parse");
+
+                let bindings = reflect(&module, &validate(&module))
+                    .expect("Test shader bindings should be valid");
+                let summary = bindings
+                    .iter()
+                    .map(|binding| (binding.name.as_str(), binding.group, binding.binding))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    summary,
+                    [
+                        ("t_diffuse", 0, 0),
+                        ("s_diffuse", 0, 1),
+                        ("particles", 0, 3),
+                        ("output", 1, 0),
+                        ("time", 2, 1),
+                    ]
+                );
+
+                assert_eq!(
+                    bindings[0].kind,
+                    BindingKind::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    }
+                );
+                assert_eq!(bindings[1].kind, BindingKind::Sampler { comparison: false });
+                assert_eq!(bindings[2].kind, BindingKind::Storage { read_only: false });
+                assert_eq!(
+                    bindings[3].kind,
+                    BindingKind::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    }
+                );
+                assert_eq!(bindings[4].kind, BindingKind::Uniform);
+
+                let sizes = bindings
+                    .iter()
+                    .map(|binding| binding.size)
+                    .collect::<Vec<_>>();
+                assert_eq!(sizes, [0, 0, 16, 0, 4]);
+
+                let visibility = bindings
+                    .iter()
+                    .map(|binding| binding.visibility)
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    visibility,
+                    [
+                        wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                        wgpu::ShaderStages::FRAGMENT,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ]
+                );
+
+                let module = naga::front::wgsl::parse_str(indoc! {"
+                    @group(0) @binding(0)
+                    var<uniform> a: f32;
+                    @group(0) @binding(0)
+                    var<uniform> b: f32;
+                "})
+                .expect("Test shader should parse");
+                let errors = reflect(&module, &validate(&module))
+                    .expect_err("Shared bindings should be rejected");
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].message, "`a` and `b` share @group(0) @binding(0)");
+            },
+            || {},
+        ));
+    }
+    #[test]
+    fn check_buffer_size() {
+        run_test(Test::new(
+            || {},
+            || {
+                let (device, _) = pollster::block_on(crate::headless::request_device(false))
+                    .expect("Test device should be available");
+                let module = naga::front::wgsl::parse_str(indoc! {"
+                    @group(0) @binding(0)
+                    var<uniform> params: array<vec4f, 64>;
+
+                    @fragment
+                    fn fs_main() -> @location(0) vec4f {
+                        return params[0];
+                    }
+                "})
+                .expect("Test shader should parse");
+                let info = validate(&module);
+
+                let layout = |size| {
+                    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                        label: None,
+                        size,
+                        usage: wgpu::BufferUsages::UNIFORM,
+                        mapped_at_creation: false,
+                    });
+                    let registry =
+                        ResourceRegistry::new().with("params", Resource::Buffer(&buffer));
+                    BindingLayout::new(&device, &module, &info, &registry, "Test")
+                        .err()
+                        .map(|errors| {
+                            errors
+                                .into_iter()
+                                .map(|error| error.message)
+                                .collect::<Vec<_>>()
+                        })
+                };
+
+                assert_eq!(
+                    layout(16),
+                    Some(vec![
+                        "Cannot bind `params`: it is 16 bytes but the shader expects at least 1024"
+                            .to_owned()
+                    ])
+                );
+                assert_eq!(layout(1024), None);
+            },
+            || {},
+        ));
+    }
+}
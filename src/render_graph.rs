@@ -0,0 +1,825 @@
+use crate::{
+    preprocessor::Defines,
+    reflection::{Resource, ResourceRegistry},
+    texture::Texture,
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+/// A pass of the render graph, as described in the config.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PassConfig {
+    pub name: String,
+    /// Shader of the pass, relative to the assets folder
+    pub shader: String,
+    #[serde(default)]
+    pub kind: PassKind,
+    /// Textures read by the shader, by binding name
+    ///
+    /// An input named `t_<name>` also binds its sampler as `s_<name>`.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, Input>,
+    /// Names of the color targets, in `@location` order, defaults to the name of the pass
+    ///
+    /// Compute passes bind them as storage textures of the same name instead.
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Preprocessor symbols defined for the shader
+    #[serde(default)]
+    pub defines: Defines,
+    /// Whether the outputs start each frame with the content of the previous one instead of
+    /// being cleared, for simulations that only update some of their pixels
+    #[serde(default)]
+    pub persistent: bool,
+    /// Image persistent outputs start with, and go back to when reset, relative to the
+    /// assets folder
+    #[serde(default)]
+    pub seed: Option<String>,
+}
+
+impl PassConfig {
+    pub fn outputs(&self) -> Vec<&str> {
+        match self.outputs.is_empty() {
+            true => vec![self.name.as_str()],
+            false => self.outputs.iter().map(String::as_str).collect(),
+        }
+    }
+
+    pub fn shader_path(&self) -> String {
+        match self.shader.ends_with(".wgsl") {
+            true => self.shader.to_owned(),
+            false => format!("{}.wgsl", self.shader),
+        }
+    }
+
+    /// Format of the outputs of the pass.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match self.kind {
+            PassKind::Render => Texture::DIFFUSE_FORMAT,
+            PassKind::Compute { .. } => Texture::STORAGE_FORMAT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum PassKind {
+    /// Draws a fullscreen triangle with `vs_main` and `fs_main`
+    #[default]
+    Render,
+    /// Dispatches `cs_main` over a grid of workgroups
+    Compute { workgroups: Workgroups },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Workgroups {
+    Fixed(u32, u32, u32),
+    /// Enough workgroups to cover every pixel of the outputs
+    Resolution,
+}
+
+impl Workgroups {
+    /// Number of workgroups to dispatch, given the `@workgroup_size` of the entry point.
+    pub fn count(&self, workgroup_size: [u32; 3], width: u32, height: u32) -> [u32; 3] {
+        match *self {
+            Workgroups::Fixed(x, y, z) => [x, y, z],
+            Workgroups::Resolution => [
+                width.div_ceil(workgroup_size[0].max(1)),
+                height.div_ceil(workgroup_size[1].max(1)),
+                1,
+            ],
+        }
+    }
+}
+
+/// Where the texture bound to an input of a pass comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum Input {
+    /// Output of a pass rendered earlier in the same frame
+    Output(String),
+    /// Output of a pass as it was at the end of the previous frame
+    Previous(String),
+    /// Image file, relative to the assets folder
+    Image(String),
+}
+
+/// Orders `passes` so that every pass comes after the ones whose output it reads, keeping the
+/// config order where it doesn't matter.
+pub fn sort(passes: &[PassConfig]) -> Result<Vec<usize>, String> {
+    let mut writers = HashMap::<&str, usize>::new();
+    for (i, pass) in passes.iter().enumerate() {
+        if let Some(other) = passes[..i].iter().find(|other| other.name == pass.name) {
+            return Err(format!("Pass `{}` is defined twice", other.name));
+        }
+
+        if pass.seed.is_some() && !pass.persistent {
+            return Err(format!(
+                "Pass `{}` has a seed but isn't persistent",
+                pass.name
+            ));
+        }
+
+        for output in pass.outputs() {
+            if let Some(&other) = writers.get(output) {
+                return Err(format!(
+                    "Output `{output}` is written by both `{}` and `{}`",
+                    passes[other].name, pass.name
+                ));
+            }
+            writers.insert(output, i);
+        }
+    }
+
+    let mut dependencies = vec![Vec::new(); passes.len()];
+    for (i, pass) in passes.iter().enumerate() {
+        for (binding, input) in pass.inputs.iter() {
+            let (Input::Output(output) | Input::Previous(output)) = input else {
+                continue;
+            };
+
+            let Some(&writer) = writers.get(output.as_str()) else {
+                return Err(format!(
+                    "Input `{binding}` of pass `{}` reads unknown output `{output}`",
+                    pass.name
+                ));
+            };
+
+            if let Input::Output(_) = input {
+                dependencies[i].push(writer);
+            }
+        }
+    }
+
+    let mut order = Vec::with_capacity(passes.len());
+    while order.len() < passes.len() {
+        let Some(next) = (0..passes.len())
+            .find(|i| !order.contains(i) && dependencies[*i].iter().all(|dep| order.contains(dep)))
+        else {
+            let cycle = (0..passes.len())
+                .filter(|i| !order.contains(i))
+                .map(|i| format!("`{}`", passes[i].name))
+                .collect::<Vec<_>>();
+            return Err(format!(
+                "Passes {} depend on each other, use `Previous` to read last frame's output",
+                cycle.join(", ")
+            ));
+        };
+        order.push(next);
+    }
+
+    Ok(order)
+}
+
+/// Render targets of the passes, and the textures they read.
+pub struct RenderGraph {
+    /// Passes in execution order
+    passes: Vec<PassConfig>,
+    targets: HashMap<String, Target>,
+    /// Image inputs and seeds, by path
+    images: HashMap<String, Texture>,
+    /// Storage buffers every pass can bind, by name
+    buffers: BTreeMap<String, wgpu::Buffer>,
+    /// Output displayed in the window
+    output: String,
+    /// Draws seeds into persistent targets
+    seed_shader: wgpu::ShaderModule,
+    /// Whether buffers have to be cleared before the next frame
+    needs_reset: bool,
+    /// Whether the current and previous textures of the targets are swapped relative to their
+    /// allocation, which decides the textures bind groups refer to
+    swapped: bool,
+}
+
+struct Target {
+    current: Texture,
+    /// Only allocated for outputs of persistent passes or read with `Input::Previous`
+    previous: Option<Texture>,
+    persistent: bool,
+    seed: Option<String>,
+    /// Whether the target has to be cleared or seeded before the next frame, if persistent
+    needs_reset: bool,
+}
+
+impl RenderGraph {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_config: &wgpu::SurfaceConfiguration,
+        passes: &[PassConfig],
+        buffers: &BTreeMap<String, u64>,
+        output: Option<&str>,
+        assets_folder: &Path,
+    ) -> Result<Self, String> {
+        let passes = sort(passes)?
+            .into_iter()
+            .map(|i| passes[i].clone())
+            .collect::<Vec<_>>();
+
+        let output = match output {
+            Some(output) => output.to_owned(),
+            None => passes
+                .last()
+                .map(|pass| pass.outputs()[0].to_owned())
+                .ok_or("The render graph has no passes")?,
+        };
+        if !passes
+            .iter()
+            .any(|pass| pass.outputs().contains(&output.as_str()))
+        {
+            return Err(format!(
+                "Displayed output `{output}` is not written by any pass"
+            ));
+        }
+
+        let image_paths = passes.iter().flat_map(|pass| {
+            let inputs = pass.inputs.values().filter_map(|input| match input {
+                Input::Image(path) => Some(path),
+                _ => None,
+            });
+            inputs.chain(pass.seed.as_ref())
+        });
+
+        let mut images = HashMap::new();
+        for path in image_paths {
+            if !images.contains_key(path) {
+                let texture = Texture::from_file(device, queue, &assets_folder.join(path))
+                    .map_err(|err| format!("Failed to load image `{path}`: {err}"))?;
+                images.insert(path.to_owned(), texture);
+            }
+        }
+
+        let buffers = buffers
+            .iter()
+            .map(|(name, &size)| {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(name),
+                    size,
+                    usage: wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_SRC
+                        | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                (name.to_owned(), buffer)
+            })
+            .collect();
+
+        let mut graph = Self {
+            passes,
+            images,
+            buffers,
+            output,
+            ..Self::empty(device)
+        };
+        graph.resize(device, surface_config);
+
+        Ok(graph)
+    }
+
+    /// Graph without any pass, displaying nothing.
+    pub fn empty(device: &wgpu::Device) -> Self {
+        Self {
+            passes: Vec::new(),
+            targets: HashMap::new(),
+            images: HashMap::new(),
+            buffers: BTreeMap::new(),
+            output: String::new(),
+            seed_shader: device
+                .create_shader_module(wgpu::include_wgsl!("fullscreen_texture.wgsl")),
+            needs_reset: false,
+            swapped: false,
+        }
+    }
+
+    /// Passes in execution order.
+    pub fn passes(&self) -> &[PassConfig] {
+        &self.passes
+    }
+
+    /// Output displayed in the window.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Pass writing `output`.
+    pub fn writer(&self, output: &str) -> Option<&PassConfig> {
+        self.passes
+            .iter()
+            .find(|pass| pass.outputs().contains(&output))
+    }
+
+    /// Texture `output` is written to during the current frame.
+    pub fn target(&self, output: &str) -> Option<&Texture> {
+        self.targets.get(output).map(|target| &target.current)
+    }
+
+    /// (Re)allocates the render targets to the size of the surface, persistent ones getting
+    /// reset.
+    pub fn resize(&mut self, device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) {
+        let read_previous = self
+            .passes
+            .iter()
+            .flat_map(|pass| pass.inputs.values())
+            .filter_map(|input| match input {
+                Input::Previous(output) => Some(output.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.targets = self
+            .passes
+            .iter()
+            .flat_map(|pass| pass.outputs().into_iter().map(move |output| (pass, output)))
+            .map(|(pass, output)| {
+                let texture = |suffix| {
+                    Texture::create_target(
+                        device,
+                        surface_config,
+                        pass.format(),
+                        &format!("{output}{suffix} Render Target"),
+                    )
+                };
+
+                let target = Target {
+                    current: texture(""),
+                    previous: (pass.persistent || read_previous.contains(&output))
+                        .then(|| texture(" (Previous)")),
+                    persistent: pass.persistent,
+                    seed: pass.seed.clone(),
+                    needs_reset: true,
+                };
+                (output.to_owned(), target)
+            })
+            .collect();
+        self.needs_reset = true;
+        self.swapped = false;
+    }
+
+    /// Side of the ping-pong of the targets the current frame is on, `0` or `1`.
+    ///
+    /// Bind groups created on one side stay valid until the targets get reallocated.
+    pub fn side(&self) -> usize {
+        self.swapped as usize
+    }
+
+    /// Clears or seeds persistent targets before the next frame.
+    pub fn reset(&mut self) {
+        self.needs_reset = true;
+        for target in self.targets.values_mut() {
+            target.needs_reset = true;
+        }
+    }
+
+    /// Takes over the targets and buffers of `previous` that are still compatible, so that
+    /// their content survives a change of the graph.
+    ///
+    /// Targets are kept if they have the same name, size and format, buffers if they have the
+    /// same name and size. Persistent targets still get reset if they didn't have a previous
+    /// frame yet or their seed changed.
+    pub fn carry_over(&mut self, mut previous: RenderGraph) {
+        let compatible = |old: &Texture, new: &Texture| {
+            old.texture.size() == new.texture.size() && old.texture.format() == new.texture.format()
+        };
+
+        for (output, target) in self.targets.iter_mut() {
+            let Some(old) = previous.targets.remove(output) else {
+                continue;
+            };
+            if !compatible(&old.current, &target.current) {
+                continue;
+            }
+
+            target.current = old.current;
+            let previous_kept = match (target.previous.as_mut(), old.previous) {
+                (Some(previous), Some(old)) => {
+                    *previous = old;
+                    true
+                }
+                (Some(_), None) => false,
+                (None, _) => true,
+            };
+            if previous_kept && old.seed == target.seed {
+                target.needs_reset = false;
+            }
+        }
+
+        for (name, buffer) in self.buffers.iter_mut() {
+            if let Some(old) = previous.buffers.remove(name)
+                && old.size() == buffer.size()
+            {
+                *buffer = old;
+            }
+        }
+        // Buffers that weren't carried over are zeroed on creation already
+        self.needs_reset = false;
+    }
+
+    /// Records the clears and seed draws of a pending reset into `encoder`.
+    pub fn apply_reset(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        if std::mem::take(&mut self.needs_reset) {
+            for buffer in self.buffers.values() {
+                encoder.clear_buffer(buffer, 0, None);
+            }
+        }
+
+        for target in self.targets.values_mut() {
+            if !target.persistent || !std::mem::take(&mut target.needs_reset) {
+                continue;
+            }
+
+            let seed = target.seed.as_ref().map(|path| {
+                let texture = &self.images[path];
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    cache: None,
+                    label: Some("Seed Pipeline"),
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        compilation_options: Default::default(),
+                        module: &self.seed_shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        compilation_options: Default::default(),
+                        module: &self.seed_shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(target.current.texture.format().into())],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                });
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Seed Bind Group"),
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&texture.view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                        },
+                    ],
+                });
+                (pipeline, bind_group)
+            });
+
+            for texture in std::iter::once(&target.current).chain(target.previous.as_ref()) {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Reset Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &texture.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+
+                if let Some((pipeline, bind_group)) = seed.as_ref() {
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_bind_group(0, bind_group, &[]);
+                    render_pass.draw(0..3, 0..1);
+                }
+            }
+        }
+    }
+
+    /// Adds the inputs of `pass` and the storage buffers to `registry`, as well as the outputs
+    /// of compute passes.
+    pub fn registry<'r>(
+        &'r self,
+        pass: &PassConfig,
+        registry: ResourceRegistry<'r>,
+    ) -> ResourceRegistry<'r> {
+        let registry = self
+            .buffers
+            .iter()
+            .fold(registry, |registry, (name, buffer)| {
+                registry.with(name.as_str(), Resource::Buffer(buffer))
+            });
+
+        let registry = match pass.kind {
+            PassKind::Render => registry,
+            PassKind::Compute { .. } => {
+                pass.outputs()
+                    .into_iter()
+                    .fold(registry, |registry, output| {
+                        registry.with(output, Resource::Texture(&self.targets[output].current))
+                    })
+            }
+        };
+
+        pass.inputs
+            .iter()
+            .fold(registry, |registry, (binding, input)| {
+                let texture = match input {
+                    Input::Output(output) => Some(&self.targets[output].current),
+                    Input::Previous(output) => self.targets[output].previous.as_ref(),
+                    Input::Image(path) => self.images.get(path),
+                };
+
+                match texture {
+                    Some(texture) => Self::with_texture(registry, binding, texture),
+                    None => registry,
+                }
+            })
+    }
+
+    /// Adds the displayed output to `registry`, as `t_diffuse` and `s_diffuse`.
+    pub fn output_registry<'r>(&'r self, registry: ResourceRegistry<'r>) -> ResourceRegistry<'r> {
+        match self.targets.get(&self.output) {
+            Some(target) => Self::with_texture(registry, "t_diffuse", &target.current),
+            None => registry,
+        }
+    }
+
+    /// Sizes of the textures bound to the inputs of `pass`, in the order of their binding
+    /// names, or of the displayed output if `pass` is `None`.
+    pub fn input_sizes(&self, pass: Option<&PassConfig>) -> Vec<wgpu::Extent3d> {
+        let Some(pass) = pass else {
+            return self
+                .targets
+                .get(&self.output)
+                .map(|target| target.current.texture.size())
+                .into_iter()
+                .collect();
+        };
+
+        pass.inputs
+            .values()
+            .map(|input| match input {
+                // The previous frame doesn't exist yet on the first one, but will be this size
+                Input::Output(output) | Input::Previous(output) => {
+                    self.targets[output].current.texture.size()
+                }
+                Input::Image(path) => self.images[path].texture.size(),
+            })
+            .collect()
+    }
+
+    fn with_texture<'r>(
+        registry: ResourceRegistry<'r>,
+        binding: &str,
+        texture: &'r Texture,
+    ) -> ResourceRegistry<'r> {
+        let sampler = format!("s_{}", binding.strip_prefix("t_").unwrap_or(binding));
+        registry
+            .with(binding, Resource::Texture(texture))
+            .with(sampler, Resource::Sampler(&texture.sampler))
+    }
+
+    /// Number of workgroups to dispatch for compute `pass`, given the `@workgroup_size` of its
+    /// entry point.
+    pub fn workgroups(&self, pass: &PassConfig, workgroup_size: [u32; 3]) -> [u32; 3] {
+        let PassKind::Compute { workgroups } = pass.kind else {
+            return [0; 3];
+        };
+
+        let size = self.targets[pass.outputs()[0]].current.texture.size();
+        workgroups.count(workgroup_size, size.width, size.height)
+    }
+
+    /// Copies the content of the previous frame over the outputs of `pass` if it is
+    /// persistent.
+    pub fn restore_outputs(&self, pass: &PassConfig, encoder: &mut wgpu::CommandEncoder) {
+        for output in pass.outputs() {
+            let target = &self.targets[output];
+            if let Some(previous) = target.previous.as_ref()
+                && target.persistent
+            {
+                encoder.copy_texture_to_texture(
+                    previous.texture.as_image_copy(),
+                    target.current.texture.as_image_copy(),
+                    target.current.texture.size(),
+                );
+            }
+        }
+    }
+
+    /// Color attachments of `pass`, in `@location` order.
+    ///
+    /// Outputs of persistent passes are loaded, to be restored with `restore_outputs` first.
+    pub fn color_attachments(
+        &self,
+        pass: &PassConfig,
+    ) -> Vec<Option<wgpu::RenderPassColorAttachment<'_>>> {
+        pass.outputs()
+            .into_iter()
+            .map(|output| {
+                let target = &self.targets[output];
+                let load = match target.persistent {
+                    true => wgpu::LoadOp::Load,
+                    false => wgpu::LoadOp::Clear(wgpu::Color::RED),
+                };
+
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &target.current.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Makes this frame's outputs the previous ones of the next frame.
+    pub fn end_frame(&mut self) {
+        for target in self.targets.values_mut() {
+            if let Some(previous) = target.previous.as_mut() {
+                std::mem::swap(&mut target.current, previous);
+            }
+        }
+        self.swapped = !self.swapped;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+    use indoc::indoc;
+
+    #[test]
+    fn render_graph_order() {
+        run_test(Test::new(
+            || {},
+            || {
+                let passes: Vec<PassConfig> = ron::from_str(indoc! {r#"
+                    [
+                        (
+                            name: "composite",
+                            shader: "composite",
+                            inputs: {
+                                "t_scene": Output("scene"),
+                                "t_bloom": Output("bloom"),
+                                "t_history": Previous("composite"),
+                            },
+                        ),
+                        (name: "bloom", shader: "bloom", inputs: {"t_diffuse": Output("bright")}),
+                        (
+                            name: "scene",
+                            shader: "scene",
+                            kind: Compute(workgroups: Resolution),
+                            outputs: ["scene", "bright"],
+                        ),
+                    ]
+                "#})
+                .expect("Test passes should deserialize");
+
+                assert_eq!(sort(&passes), Ok(vec![2, 1, 0]));
+                assert_eq!(passes[2].outputs(), ["scene", "bright"]);
+                assert_eq!(passes[1].outputs(), ["bloom"]);
+                assert_eq!(passes[1].shader_path(), "bloom.wgsl");
+                assert_eq!(passes[1].kind, PassKind::Render);
+                assert_eq!(passes[2].format(), Texture::STORAGE_FORMAT);
+
+                let PassKind::Compute { workgroups } = passes[2].kind else {
+                    panic!("`scene` should be a compute pass");
+                };
+                assert_eq!(workgroups.count([8, 8, 1], 600, 400), [75, 50, 1]);
+                assert_eq!(workgroups.count([16, 16, 1], 600, 401), [38, 26, 1]);
+                assert_eq!(
+                    Workgroups::Fixed(4, 2, 1).count([8, 8, 1], 600, 400),
+                    [4, 2, 1]
+                );
+
+                let mut cyclic = passes.clone();
+                cyclic[2].inputs.insert(
+                    "t_diffuse".to_owned(),
+                    Input::Output("composite".to_owned()),
+                );
+                assert_eq!(
+                    sort(&cyclic),
+                    Err(
+                        "Passes `composite`, `bloom`, `scene` depend on each other, use \
+                         `Previous` to read last frame's output"
+                            .to_owned()
+                    )
+                );
+
+                let mut unknown = passes.clone();
+                unknown[1].inputs.insert(
+                    "t_diffuse".to_owned(),
+                    Input::Previous("missing".to_owned()),
+                );
+                assert!(sort(&unknown).is_err());
+
+                let mut unseeded = passes.clone();
+                unseeded[0].seed = Some("noise.png".to_owned());
+                assert!(sort(&unseeded).is_err());
+                unseeded[0].persistent = true;
+                assert!(sort(&unseeded).is_ok());
+
+                let mut duplicate = passes;
+                duplicate[1].outputs = vec!["scene".to_owned()];
+                assert_eq!(
+                    sort(&duplicate),
+                    Err("Output `scene` is written by both `bloom` and `scene`".to_owned())
+                );
+            },
+            || {},
+        ));
+    }
+
+    #[test]
+    fn carry_over_targets() {
+        run_test(Test::new(
+            || {},
+            || {
+                let (device, queue) = pollster::block_on(crate::headless::request_device(false))
+                    .expect("Test device should be available");
+                let graph = |passes: &str, buffers: &[(&str, u64)], size: u32| {
+                    let passes: Vec<PassConfig> =
+                        ron::from_str(passes).expect("Test passes should deserialize");
+                    let surface_config = wgpu::SurfaceConfiguration {
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                        format: Texture::DIFFUSE_FORMAT,
+                        width: size,
+                        height: size,
+                        present_mode: wgpu::PresentMode::AutoNoVsync,
+                        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+                        view_formats: Vec::new(),
+                        desired_maximum_frame_latency: 1,
+                    };
+                    let buffers = buffers
+                        .iter()
+                        .map(|&(name, size)| (name.to_owned(), size))
+                        .collect();
+                    RenderGraph::new(
+                        &device,
+                        &queue,
+                        &surface_config,
+                        &passes,
+                        &buffers,
+                        None,
+                        Path::new("assets"),
+                    )
+                    .expect("Test graph should be valid")
+                };
+                let id = |graph: &RenderGraph, output: &str| {
+                    graph.targets[output].current.texture.global_id()
+                };
+
+                let passes = r#"[
+                    (name: "sim", shader: "sim", persistent: true),
+                    (name: "image", shader: "image", inputs: {"t_sim": Output("sim")}),
+                ]"#;
+                let buffers = [("particles", 64), ("grid", 32)];
+
+                // Same name, size and format
+                let old = graph(passes, &buffers, 16);
+                let (sim, image) = (id(&old, "sim"), id(&old, "image"));
+                let particles = old.buffers["particles"].global_id();
+                let mut new = graph(passes, &[("particles", 64), ("grid", 16)], 16);
+                new.carry_over(old);
+                assert_eq!(id(&new, "sim"), sim);
+                assert_eq!(id(&new, "image"), image);
+                assert!(!new.targets["sim"].needs_reset);
+                assert_eq!(new.buffers["particles"].global_id(), particles);
+                assert_eq!(new.buffers["grid"].size(), 16);
+
+                // Size changed
+                let old = graph(passes, &buffers, 16);
+                let sim = id(&old, "sim");
+                let mut new = graph(passes, &buffers, 32);
+                new.carry_over(old);
+                assert_ne!(id(&new, "sim"), sim);
+                assert!(new.targets["sim"].needs_reset);
+
+                // Format changed
+                let old = graph(passes, &buffers, 16);
+                let sim = id(&old, "sim");
+                let compute = passes.replace(
+                    r#"persistent: true"#,
+                    r#"persistent: true, kind: Compute(workgroups: Resolution)"#,
+                );
+                let mut new = graph(&compute, &buffers, 16);
+                new.carry_over(old);
+                assert_ne!(id(&new, "sim"), sim);
+                assert!(new.targets["sim"].needs_reset);
+
+                // Became persistent, without a previous frame to restore
+                let old = graph(&passes.replace("persistent: true", ""), &buffers, 16);
+                let sim = id(&old, "sim");
+                let mut new = graph(passes, &buffers, 16);
+                new.carry_over(old);
+                assert_eq!(id(&new, "sim"), sim);
+                assert!(new.targets["sim"].needs_reset);
+            },
+            || {},
+        ));
+    }
+}
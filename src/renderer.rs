@@ -0,0 +1,854 @@
+use crate::{
+    capture::{Capture, Readback},
+    clock::SharedClock,
+    diagnostic::{Diagnostic, Label},
+    globals::Globals,
+    mouse::MouseData,
+    params::{self, Params},
+    preprocessor,
+    reflection::{BindingLayout, Resource, ResourceRegistry},
+    render_graph::{self, RenderGraph},
+    shader_graph::{self, IncludePaths},
+    shader_source::{self, LayeredSource, ShaderSource},
+    utils::{civil_from_days, SceneTime, TimeConfig},
+    Config, Updateable,
+};
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use {anyhow::anyhow, wgpu::util::DeviceExt};
+
+/// What a `Pipeline` gets compiled into.
+enum PipelineKind {
+    /// Fullscreen triangle drawn with `vs_main` and `fs_main`
+    Render {
+        /// Formats of the color targets, in `@location` order
+        targets: Vec<wgpu::TextureFormat>,
+    },
+    /// Dispatch of `cs_main`
+    Compute,
+}
+
+enum CompiledPipeline {
+    Render(wgpu::RenderPipeline),
+    Compute {
+        pipeline: wgpu::ComputePipeline,
+        /// `@workgroup_size` of the entry point
+        workgroup_size: [u32; 3],
+    },
+}
+
+struct Pipeline {
+    label: String,
+    /// Final file of the shader graph
+    path: PathBuf,
+    defines: preprocessor::Defines,
+    includes: IncludePaths,
+    kind: PipelineKind,
+    /// Missing if the graph couldn't be built, e.g. because of a preprocessor error
+    shader: Option<shader_graph::ShaderGraph>,
+    /// Last pipeline that compiled successfully
+    pipeline: Option<(CompiledPipeline, BindingLayout)>,
+    /// Bind groups of `pipeline` for each side of the ping-pong of the render targets,
+    /// created on first use and dropped when the pipeline or the targets change
+    bind_groups: [Option<Vec<wgpu::BindGroup>>; 2],
+    /// Diagnostics of the last reload, empty if it succeeded
+    errors: Vec<Diagnostic>,
+}
+
+impl Pipeline {
+    /// Pipeline of the shader at `path`, which only gets compiled by `reload`.
+    fn new(
+        label: String,
+        path: PathBuf,
+        defines: preprocessor::Defines,
+        includes: IncludePaths,
+        kind: PipelineKind,
+    ) -> Self {
+        Self {
+            label,
+            path,
+            defines,
+            includes,
+            kind,
+            shader: None,
+            pipeline: None,
+            bind_groups: Default::default(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the shader graph from its final file and recreates the pipeline from it,
+    /// printing diagnostics for anything that goes wrong.
+    ///
+    /// The graph is replaced even if compilation fails so that newly included files get
+    /// watched, but the previous pipeline is kept around until the shader compiles again.
+    fn reload(
+        &mut self,
+        device: &wgpu::Device,
+        source: &dyn ShaderSource,
+        registry: &ResourceRegistry,
+    ) {
+        let shader = match shader_graph::ShaderGraph::try_from_final_with_source(
+            self.path.as_path(),
+            self.defines.clone(),
+            self.includes.clone(),
+            source,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                self.set_errors(vec![err.into()]);
+                return;
+            }
+        };
+
+        match Renderer::create_pipeline(device, &self.kind, &shader, registry, self.label.as_str())
+        {
+            Ok(pipeline) => {
+                self.pipeline = Some(pipeline);
+                self.bind_groups = Default::default();
+                self.set_errors(Vec::new());
+            }
+            Err(diagnostics) => self.set_errors(diagnostics),
+        }
+        self.shader = Some(shader);
+    }
+
+    fn set_errors(&mut self, errors: Vec<Diagnostic>) {
+        if errors.is_empty() && !self.errors.is_empty() {
+            println!("{} compiled successfully", self.label);
+        }
+
+        for diagnostic in errors.iter() {
+            eprint!("{diagnostic}");
+        }
+        self.errors = errors;
+    }
+
+    /// Creates the bind groups of `side` from `registry` unless they are cached already.
+    fn cache_bind_groups<'r>(
+        &mut self,
+        device: &wgpu::Device,
+        side: usize,
+        registry: impl FnOnce() -> ResourceRegistry<'r>,
+    ) {
+        if let Some((_, layout)) = self.pipeline.as_ref()
+            && self.bind_groups[side].is_none()
+        {
+            self.bind_groups[side] = layout.bind_groups(device, &registry());
+        }
+    }
+
+    /// Compiled pipeline and its cached bind groups for `side`, `None` if it never compiled or
+    /// a resource was missing.
+    fn bound(&self, side: usize) -> Option<(&CompiledPipeline, &[wgpu::BindGroup])> {
+        let (pipeline, _) = self.pipeline.as_ref()?;
+        Some((pipeline, self.bind_groups[side].as_deref()?))
+    }
+
+    /// Whether any of the canonical `paths` is in the include closure of the shader, or is its
+    /// final file if the graph couldn't be built.
+    fn depends_on(&self, paths: &[PathBuf]) -> bool {
+        match self.shader.as_ref() {
+            Some(shader) => paths.iter().any(|path| shader.depends_on(path)),
+            None => self
+                .path
+                .canonicalize()
+                .is_ok_and(|path| paths.contains(&path)),
+        }
+    }
+
+    /// Canonical paths of the files this pipeline should be reloaded on changes of.
+    fn paths(&self) -> Vec<PathBuf> {
+        self.shader
+            .iter()
+            .flat_map(|shader| shader.paths().map(Path::to_path_buf))
+            .chain(self.path.canonicalize().ok())
+            .collect()
+    }
+}
+
+/// Renders the passes of the render graph and blits its output into a target texture.
+///
+/// Owns everything but the surface, so that it can render into a window as well as offscreen.
+pub(crate) struct Renderer {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    /// Size and format of the textures `render` draws into
+    pub target: wgpu::SurfaceConfiguration,
+
+    render_pipelines: Vec<Pipeline>,
+    /// Missing while the renderer is empty
+    blit_pipeline: Option<Pipeline>,
+
+    render_graph: RenderGraph,
+
+    /// Uniforms of the current frame, shared by every pass but for the channel resolutions
+    globals: Globals,
+    /// `globals` buffer of every pass, then of the blit
+    globals_buffers: Vec<wgpu::Buffer>,
+    /// Time of the clock at the previous update
+    previous_clock: Duration,
+    /// Wall time the renderer was created at, which `globals.date` starts from
+    start_date: Duration,
+
+    pub params: Params,
+    params_buffer: wgpu::Buffer,
+    /// Where shaders are read from, `generated/params.wgsl` being in memory
+    shader_source: LayeredSource,
+
+    pub time: SceneTime,
+    pub mouse: MouseData,
+}
+
+impl Renderer {
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: wgpu::SurfaceConfiguration,
+        config: &Config,
+        clock: SharedClock,
+    ) -> Result<Self, String> {
+        let mut renderer = Self::empty(device, queue, target, &config.time, clock);
+        renderer.reconfigure(config)?;
+        Ok(renderer)
+    }
+
+    /// Renderer without any pass, which only clears what it renders into until it gets
+    /// reconfigured with a valid graph.
+    pub fn empty(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: wgpu::SurfaceConfiguration,
+        time: &TimeConfig,
+        clock: SharedClock,
+    ) -> Self {
+        let render_graph = RenderGraph::empty(&device);
+        let globals_buffers = Self::create_globals_buffers(&device, &render_graph);
+        let (params, params_buffer) =
+            Self::create_params(&device, &[]).expect("Empty params should be valid");
+
+        Self {
+            time: SceneTime::new(clock.clone(), time),
+            mouse: MouseData::new(1000, clock.clone()),
+            device,
+            queue,
+            target,
+            render_pipelines: Vec::new(),
+            blit_pipeline: None,
+            render_graph,
+            globals: Globals::default(),
+            globals_buffers,
+            previous_clock: clock.now(),
+            start_date: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+            shader_source: Self::shader_source(&params),
+            params,
+            params_buffer,
+        }
+    }
+
+    /// Whether no graph was applied yet, the one of the config having been invalid.
+    pub fn is_empty(&self) -> bool {
+        self.render_graph.passes().is_empty()
+    }
+
+    /// Switches to the passes, buffers and params of `config`, rebuilding every pipeline.
+    ///
+    /// Targets and buffers that still fit the new graph keep their content, and params their
+    /// adjusted values, while time, mouse and frame count carry on. Nothing changes if the new
+    /// graph or params are invalid.
+    pub fn reconfigure(&mut self, config: &Config) -> Result<(), String> {
+        let assets_folder = config.assets_folder();
+
+        let render_graph = RenderGraph::new(
+            &self.device,
+            &self.queue,
+            &self.target,
+            &config.passes,
+            &config.buffers,
+            config.output.as_deref(),
+            &assets_folder,
+        )?;
+        let (mut params, params_buffer) = Self::create_params(&self.device, &config.params)?;
+        let globals_buffers = Self::create_globals_buffers(&self.device, &render_graph);
+
+        params.carry_over(&self.params);
+        let previous = std::mem::replace(&mut self.render_graph, render_graph);
+        self.render_graph.carry_over(previous);
+        self.globals_buffers = globals_buffers;
+        self.shader_source = Self::shader_source(&params);
+        self.params = params;
+        self.params_buffer = params_buffer;
+
+        let (render_pipelines, blit_pipeline) = Self::create_pipelines(
+            &self.device,
+            self.target.format,
+            &self.render_graph,
+            &self.globals_buffers,
+            &self.params_buffer,
+            &IncludePaths::new(&assets_folder, &config.include_paths),
+            &self.shader_source,
+        );
+        self.render_pipelines = render_pipelines;
+        self.blit_pipeline = Some(blit_pipeline);
+        Ok(())
+    }
+
+    /// Default source of the shaders, under the WGSL generated from `params`.
+    fn shader_source(params: &Params) -> LayeredSource {
+        LayeredSource::new()
+            .with(params.source())
+            .with(shader_source::default_source())
+    }
+
+    /// `globals` buffer of every pass of `render_graph`, then of the blit.
+    fn create_globals_buffers(
+        device: &wgpu::Device,
+        render_graph: &RenderGraph,
+    ) -> Vec<wgpu::Buffer> {
+        (0..=render_graph.passes().len())
+            .map(|_| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Globals Buffer"),
+                    contents: bytemuck::cast_slice(&[Globals::default()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+            })
+            .collect()
+    }
+
+    /// Params of the config and their uniform buffer.
+    fn create_params(
+        device: &wgpu::Device,
+        configs: &[params::ParamConfig],
+    ) -> Result<(Params, wgpu::Buffer), String> {
+        let params = Params::new(configs)?;
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params Buffer"),
+            contents: &params.bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Ok((params, buffer))
+    }
+
+    /// Pipelines of the passes of `render_graph` in order, and the blit pipeline.
+    fn create_pipelines(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        render_graph: &RenderGraph,
+        globals_buffers: &[wgpu::Buffer],
+        params_buffer: &wgpu::Buffer,
+        includes: &IncludePaths,
+        source: &dyn ShaderSource,
+    ) -> (Vec<Pipeline>, Pipeline) {
+        let render_pipelines = render_graph
+            .passes()
+            .iter()
+            .zip(globals_buffers.iter())
+            .map(|(pass, globals)| {
+                let mut pipeline = Pipeline::new(
+                    format!("Pipeline ({})", pass.name),
+                    includes.root.join(pass.shader_path()),
+                    pass.defines.clone(),
+                    includes.clone(),
+                    match pass.kind {
+                        render_graph::PassKind::Render => PipelineKind::Render {
+                            targets: vec![pass.format(); pass.outputs().len()],
+                        },
+                        render_graph::PassKind::Compute { .. } => PipelineKind::Compute,
+                    },
+                );
+                pipeline.reload(
+                    device,
+                    source,
+                    &render_graph.registry(pass, Self::resource_registry(globals, params_buffer)),
+                );
+                pipeline
+            })
+            .collect();
+
+        let mut blit_pipeline = Pipeline::new(
+            "Blit Pipeline".to_owned(),
+            includes.root.join("blit.wgsl"),
+            preprocessor::Defines::new(),
+            includes.clone(),
+            PipelineKind::Render {
+                targets: vec![format],
+            },
+        );
+        blit_pipeline.reload(
+            device,
+            source,
+            &render_graph.output_registry(Self::resource_registry(
+                globals_buffers
+                    .last()
+                    .expect("Blit should have a globals buffer"),
+                params_buffer,
+            )),
+        );
+
+        (render_pipelines, blit_pipeline)
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        kind: &PipelineKind,
+        shader_graph: &shader_graph::ShaderGraph,
+        registry: &ResourceRegistry,
+        label: &str,
+    ) -> Result<(CompiledPipeline, BindingLayout), Vec<Diagnostic>> {
+        let finished = shader_graph.finish().map_err(|err| vec![err.into()])?;
+
+        // naga errors carry spans, which can be mapped back to the original files
+        let module = naga::front::wgsl::parse_str(&finished.code)
+            .map_err(|err| vec![Diagnostic::from_parse_error(&err, &finished)])?;
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|err| vec![Diagnostic::from_validation_error(&err, &finished)])?;
+
+        let file_label = || shader_graph.last().map(|last| Label::file(&last.path));
+
+        let workgroup_size = match kind {
+            PipelineKind::Render { .. } => [0; 3],
+            PipelineKind::Compute => module
+                .entry_points
+                .iter()
+                .find(|entry_point| {
+                    entry_point.name == "cs_main" && entry_point.stage == naga::ShaderStage::Compute
+                })
+                .map(|entry_point| entry_point.workgroup_size)
+                .ok_or_else(|| {
+                    let diagnostic = Diagnostic::error("Missing `@compute fn cs_main` entry point");
+                    vec![match file_label() {
+                        Some(label) => diagnostic.with_label(label),
+                        None => diagnostic,
+                    }]
+                })?,
+        };
+
+        let layout =
+            BindingLayout::new(device, &module, &info, registry, label).map_err(|errors| {
+                errors
+                    .iter()
+                    .map(|err| Diagnostic::from_binding_error(err, &finished))
+                    .collect::<Vec<_>>()
+            })?;
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let mut shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(finished.code.as_str().into()),
+        });
+        if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+            let comp_info = pollster::block_on(shader.get_compilation_info());
+            let mut diagnostics = comp_info
+                .messages
+                .iter()
+                .map(|message| Diagnostic::from_compilation_message(message, &finished))
+                .collect::<Vec<_>>();
+            if diagnostics.is_empty() {
+                diagnostics.push(Diagnostic::error(err));
+            }
+            return Err(diagnostics);
+        }
+
+        // Catches mismatches between the shader and the pipeline layout
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = match kind {
+            PipelineKind::Render { targets } => CompiledPipeline::Render(
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    cache: None,
+                    label: Some(label),
+                    layout: Some(&layout.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        compilation_options: Default::default(),
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        compilation_options: Default::default(),
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &targets
+                            .iter()
+                            .map(|&format| {
+                                Some(wgpu::ColorTargetState {
+                                    format,
+                                    blend: Some(wgpu::BlendState::REPLACE),
+                                    write_mask: wgpu::ColorWrites::ALL,
+                                })
+                            })
+                            .collect::<Vec<_>>(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                }),
+            ),
+            PipelineKind::Compute => CompiledPipeline::Compute {
+                pipeline: device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    cache: None,
+                    label: Some(label),
+                    layout: Some(&layout.pipeline_layout),
+                    module: &shader,
+                    entry_point: "cs_main",
+                    compilation_options: Default::default(),
+                }),
+                workgroup_size,
+            },
+        };
+        if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+            let diagnostic = Diagnostic::error(format!("Failed to create {label}")).with_note(err);
+            return Err(vec![match file_label() {
+                Some(label) => diagnostic.with_label(label),
+                None => diagnostic,
+            }]);
+        }
+
+        Ok((pipeline, layout))
+    }
+
+    /// Resources every shader can bind to by declaring a global of the same name, on top of
+    /// the inputs of its pass.
+    fn resource_registry<'r>(
+        globals: &'r wgpu::Buffer,
+        params: &'r wgpu::Buffer,
+    ) -> ResourceRegistry<'r> {
+        ResourceRegistry::new()
+            .with("globals", Resource::Buffer(globals))
+            .with("params", Resource::Buffer(params))
+    }
+
+    fn pipelines(&self) -> impl Iterator<Item = &Pipeline> {
+        self.render_pipelines
+            .iter()
+            .chain(self.blit_pipeline.as_ref())
+    }
+
+    fn pipelines_mut(&mut self) -> impl Iterator<Item = &mut Pipeline> {
+        self.render_pipelines
+            .iter_mut()
+            .chain(self.blit_pipeline.as_mut())
+    }
+
+    /// Text of the error overlay, listing the diagnostics of every failing pipeline.
+    pub fn error_text(&self) -> String {
+        self.pipelines()
+            .flat_map(|pipeline| pipeline.errors.iter())
+            .map(Diagnostic::to_string)
+            .collect()
+    }
+
+    /// Whether every pipeline compiled on its last reload.
+    pub fn is_valid(&self) -> bool {
+        self.pipelines().all(|pipeline| pipeline.errors.is_empty())
+    }
+
+    /// Canonical paths of the files any pipeline should be reloaded on changes of.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.pipelines().flat_map(Pipeline::paths).collect()
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.target.width = width;
+        self.target.height = height;
+
+        self.render_graph.resize(&self.device, &self.target);
+        for pipeline in self.pipelines_mut() {
+            pipeline.bind_groups = Default::default();
+        }
+    }
+
+    /// Clears the outputs and restarts the frame count.
+    pub fn reset(&mut self) {
+        self.render_graph.reset();
+        self.globals.frame = 0;
+    }
+
+    /// Writes the uniforms of the next frame, at the current scene time.
+    pub fn update(&mut self) {
+        let globals = &mut self.globals;
+        globals.resolution = [self.target.width as f32, self.target.height as f32, 1.];
+
+        // Time
+        let time = self.time.seconds() as f32;
+        globals.time_delta = match globals.frame {
+            0 => 0.,
+            _ => time - globals.time,
+        };
+        globals.time = time;
+        let now = self.time.clock.now();
+        let frame_duration = now.saturating_sub(self.previous_clock).as_secs_f32();
+        self.previous_clock = now;
+        globals.frame_rate = match frame_duration > 0. {
+            true => 1. / frame_duration,
+            false => 0.,
+        };
+
+        // Date, moving along with the scene time
+        let date = self.start_date.as_secs_f64() + time as f64;
+        let (year, month, day) = civil_from_days(date.div_euclid(86400.) as i64);
+        globals.date = [
+            year as f32,
+            (month - 1) as f32,
+            day as f32,
+            date.rem_euclid(86400.) as f32,
+        ];
+
+        // Mouse
+        self.mouse.update(&self.queue);
+        globals.set_mouse(&mut self.mouse);
+
+        self.queue
+            .write_buffer(&self.params_buffer, 0, &self.params.bytes());
+
+        // Channel resolutions differ between passes
+        let passes = self.render_graph.passes().iter().map(Some);
+        for (pass, buffer) in passes
+            .chain(std::iter::once(None))
+            .zip(self.globals_buffers.iter())
+        {
+            let sizes = self.render_graph.input_sizes(pass);
+            let mut globals = *globals;
+            for (channel, size) in globals.channel_resolution.iter_mut().zip(sizes) {
+                *channel = [size.width as f32, size.height as f32, 1., 1.];
+            }
+            self.queue
+                .write_buffer(buffer, 0, bytemuck::cast_slice(&[globals]));
+        }
+    }
+
+    /// Rebuilds every pass whose include closure contains one of `updated_paths`.
+    pub fn reload(&mut self, updated_paths: &[PathBuf]) {
+        // The last globals buffer is the one of the blit
+        let registries = self
+            .render_graph
+            .passes()
+            .iter()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .zip(self.globals_buffers.iter())
+            .map(|(pass, globals)| {
+                let registry = Self::resource_registry(globals, &self.params_buffer);
+                match pass {
+                    Some(pass) => self.render_graph.registry(pass, registry),
+                    None => self.render_graph.output_registry(registry),
+                }
+            });
+        for (pipeline, registry) in self
+            .render_pipelines
+            .iter_mut()
+            .chain(self.blit_pipeline.as_mut())
+            .zip(registries)
+        {
+            if pipeline.depends_on(updated_paths) {
+                pipeline.reload(&self.device, &self.shader_source, &registry);
+            }
+        }
+    }
+
+    /// Records the passes of the graph, then the blit of its output into `view`.
+    pub fn render(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        self.render_graph.apply_reset(&self.device, encoder);
+        let side = self.render_graph.side();
+
+        // Passes of the graph
+        for ((pass, render_pipeline), globals) in self
+            .render_graph
+            .passes()
+            .iter()
+            .zip(self.render_pipelines.iter_mut())
+            .zip(self.globals_buffers.iter())
+        {
+            render_pipeline.cache_bind_groups(&self.device, side, || {
+                self.render_graph
+                    .registry(pass, Self::resource_registry(globals, &self.params_buffer))
+            });
+            // Passes that never compiled are skipped altogether
+            let Some((pipeline, bind_groups)) = render_pipeline.bound(side) else {
+                continue;
+            };
+
+            self.render_graph.restore_outputs(pass, encoder);
+
+            match pipeline {
+                CompiledPipeline::Render(pipeline) => {
+                    let color_attachments = self.render_graph.color_attachments(pass);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&format!("Render Pass ({})", pass.name)),
+                        color_attachments: &color_attachments,
+                        depth_stencil_attachment: None,
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                    });
+
+                    render_pass.set_pipeline(pipeline);
+                    for (group, bind_group) in bind_groups.iter().enumerate() {
+                        render_pass.set_bind_group(group as u32, bind_group, &[]);
+                    }
+
+                    render_pass.draw(0..3, 0..1);
+                }
+                CompiledPipeline::Compute {
+                    pipeline,
+                    workgroup_size,
+                } => {
+                    let mut compute_pass =
+                        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some(&format!("Compute Pass ({})", pass.name)),
+                            timestamp_writes: None,
+                        });
+
+                    compute_pass.set_pipeline(pipeline);
+                    for (group, bind_group) in bind_groups.iter().enumerate() {
+                        compute_pass.set_bind_group(group as u32, bind_group, &[]);
+                    }
+
+                    let [x, y, z] = self.render_graph.workgroups(pass, *workgroup_size);
+                    compute_pass.dispatch_workgroups(x, y, z);
+                }
+            }
+        }
+
+        let blit_globals = self
+            .globals_buffers
+            .last()
+            .expect("Blit should have a globals buffer");
+        if let Some(blit_pipeline) = self.blit_pipeline.as_mut() {
+            blit_pipeline.cache_bind_groups(&self.device, side, || {
+                self.render_graph
+                    .output_registry(Self::resource_registry(blit_globals, &self.params_buffer))
+            });
+        }
+        self.blit(encoder, view);
+    }
+
+    /// Makes this frame's outputs the previous ones of the next frame, once it's submitted,
+    /// counts it and ticks the clock.
+    pub fn end_frame(&mut self) {
+        self.render_graph.end_frame();
+        self.globals.frame += 1;
+        self.time.clock.tick();
+    }
+
+    /// Draws the displayed output into `view`, with the bind groups cached by `render`.
+    fn blit(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLUE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        if let Some((CompiledPipeline::Render(pipeline), bind_groups)) = self
+            .blit_pipeline
+            .as_ref()
+            .and_then(|blit_pipeline| blit_pipeline.bound(self.render_graph.side()))
+        {
+            render_pass.set_pipeline(pipeline);
+            for (group, bind_group) in bind_groups.iter().enumerate() {
+                render_pass.set_bind_group(group as u32, bind_group, &[]);
+            }
+
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Records the readback of `output`, or of the final image without the overlay.
+    ///
+    /// Must be called after `render` and before `end_frame`.
+    pub fn capture(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        output: Option<&str>,
+    ) -> anyhow::Result<Capture> {
+        let (texture, readback) = match output {
+            Some(output) => {
+                let texture = self
+                    .render_graph
+                    .target(output)
+                    .ok_or_else(|| anyhow!("No pass writes output `{output}`"))?;
+                let readback = Readback::new(&self.device, &texture.texture)?;
+                readback.copy(encoder, &texture.texture);
+                (None, readback)
+            }
+            None => {
+                let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Capture Target"),
+                    size: wgpu::Extent3d {
+                        width: self.target.width,
+                        height: self.target.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.target.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                    view_formats: &[],
+                });
+                let readback = Readback::new(&self.device, &texture)?;
+                self.blit(
+                    encoder,
+                    &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                );
+                readback.copy(encoder, &texture);
+                (Some(texture), readback)
+            }
+        };
+
+        Ok(Capture {
+            readback,
+            texture,
+            metadata: self.metadata(output),
+        })
+    }
+
+    /// PNG text metadata of a capture of `output`, or of the final image.
+    pub fn metadata(&self, output: Option<&str>) -> Vec<(&'static str, String)> {
+        let output = output.unwrap_or(self.render_graph.output());
+        let shader = self
+            .render_graph
+            .writer(output)
+            .map(|pass| pass.shader_path())
+            .unwrap_or_default();
+
+        vec![
+            ("Software", env!("CARGO_PKG_NAME").to_owned()),
+            ("Shader", shader),
+            ("Output", output.to_owned()),
+            ("Time", self.globals.time.to_string()),
+        ]
+    }
+}
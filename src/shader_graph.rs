@@ -1,3 +1,11 @@
+use crate::{
+    diagnostic::Label,
+    generated,
+    preprocessor::{self, Conditions, Defines},
+    shader_source::{self, ShaderSource},
+    std_lib,
+};
+
 use std::{
     collections::{HashMap, VecDeque},
     fmt::Display,
@@ -8,28 +16,182 @@ use std::{
 
 pub struct ShaderGraph {
     nodes: HashMap<PathBuf, Rc<ShaderGraphNode>>,
+    /// Symbols defined so far while adding nodes
+    defines: Defines,
+    /// Files currently being processed, along with the line of their include being followed
+    include_stack: Vec<(PathBuf, u32)>,
+    includes: IncludePaths,
+}
+
+/// Where includes are looked up, besides next to the including file.
+#[derive(Debug, Clone, Default)]
+pub struct IncludePaths {
+    /// Folder `@/` paths start from, usually the assets folder
+    pub root: PathBuf,
+    /// Folders `<...>` includes are looked up in, in order, before `root` and the library
+    pub search_paths: Vec<PathBuf>,
+}
+
+impl IncludePaths {
+    /// Rooted at `assets_folder`, which relative search paths start from as well.
+    pub fn new(assets_folder: &Path, search_paths: &[String]) -> Self {
+        Self {
+            root: assets_folder.to_path_buf(),
+            search_paths: search_paths
+                .iter()
+                .map(|path| assets_folder.join(path))
+                .collect(),
+        }
+    }
+
+    /// Paths `include` may refer to, from the preferred one to the last resort.
+    ///
+    /// `"path"` is looked up next to the including file in `folder`, then like `<path>` in
+    /// the search paths, the root and the standard library. `@/path` is only looked up from
+    /// the root, and generated WGSL always comes first.
+    fn candidates(&self, include: &str, quoted: bool, folder: &Path) -> Vec<PathBuf> {
+        if let Some(path) = include.strip_prefix("@/") {
+            return vec![self.root.join(path)];
+        }
+
+        let mut candidates = generated::resolve(include).into_iter().collect::<Vec<_>>();
+        if quoted {
+            candidates.push(folder.join(include));
+        }
+        candidates.extend(
+            self.search_paths
+                .iter()
+                .chain([&self.root])
+                .map(|path| path.join(include)),
+        );
+        candidates.extend(std_lib::resolve(include));
+        candidates
+    }
 }
 
 pub struct ShaderGraphNode {
     deps: Vec<Rc<ShaderGraphNode>>,
     pub path: PathBuf,
     pub code: String,
+    /// Original (1-based) line number of each line in `code`
+    pub lines: Vec<u32>,
+}
+
+/// Concatenated shader code along with where each of its lines comes from.
+pub struct FinishedShader {
+    pub code: String,
+    pub source_map: SourceMap,
+}
+
+/// Maps lines of a [`FinishedShader`] back to the file and line they were included from.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<PathBuf>,
+    /// Index into `files` and original line, for each output line
+    lines: Vec<(usize, u32)>,
+}
+
+/// Strips the working directory from `path` to keep it short when displayed.
+fn display_path(path: &Path) -> PathBuf {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Note listing the shaders next to `missing_path`, to help spot a typo in an include.
+fn available_shaders(source: &dyn ShaderSource, missing_path: &Path) -> Option<String> {
+    let folder = source.canonicalize(missing_path.parent()?).ok()?;
+    let mut names = source
+        .list(&folder)
+        .ok()?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wgsl"))
+        .filter_map(|path| Some(format!("`{}`", path.file_stem()?.to_string_lossy())))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    (!names.is_empty()).then(|| {
+        format!(
+            "shaders in `{}`: {}",
+            display_path(&folder).display(),
+            names.join(", ")
+        )
+    })
+}
+
+impl SourceMap {
+    fn push_node(&mut self, node: &ShaderGraphNode) {
+        let file = self.files.len();
+        self.files.push(display_path(&node.path));
+        self.lines
+            .extend(node.lines.iter().map(|&line| (file, line)));
+    }
+
+    /// Finds the original location of the given (1-based) line of the finished shader.
+    pub fn lookup(&self, line: u32) -> Option<SourceLocation<'_>> {
+        let &(file, line) = self.lines.get(line.checked_sub(1)? as usize)?;
+
+        Some(SourceLocation {
+            path: self.files[file].as_path(),
+            line,
+            column: None,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SourceLocation<'a> {
+    pub path: &'a Path,
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+impl Display for SourceLocation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{column}")?;
+        }
+        Ok(())
+    }
 }
 
 impl ShaderGraph {
-    fn try_add_node(&mut self, path: &Path) -> Result<Rc<ShaderGraphNode>, ShaderError> {
-        let canon_path = path.canonicalize()?;
-        let file = std::fs::File::open(path)?;
-        let mut reader = std::io::BufReader::new(file);
+    fn try_add_node(
+        &mut self,
+        source: &dyn ShaderSource,
+        path: &Path,
+    ) -> Result<Rc<ShaderGraphNode>, ShaderError> {
+        let file_err = |err: std::io::Error| ShaderError::from(err).with_label(Label::file(path));
+
+        let canon_path = source.canonicalize(path).map_err(file_err)?;
+        let content = source.read(&canon_path).map_err(file_err)?;
+        let mut reader = content.as_bytes();
+        self.include_stack.push((canon_path.clone(), 0));
 
         let mut deps = Vec::new();
         let mut code = String::new();
+        let mut lines = Vec::new();
+        let mut conditions = Conditions::default();
 
         let mut line = String::new();
         let mut line_number = 0;
-        while reader.read_line(&mut line)? != 0 {
+        while reader.read_line(&mut line).map_err(file_err)? != 0 {
+            line_number += 1;
+
+            if !line.starts_with("//%") && !conditions.is_active() {
+                line.clear();
+                continue;
+            }
+
             if !line.starts_with("//%") {
+                // Keep the next file from being glued onto a last line without newline
+                if !line.ends_with('\n') {
+                    line.push('\n');
+                }
                 code.push_str(line.as_str());
+                lines.push(line_number);
                 line.clear();
                 continue;
             }
@@ -40,42 +202,124 @@ impl ShaderGraph {
                 continue;
             }
 
-            let err =
-                |variant, msg: &dyn Display| Err((format!("{line_number}: {msg}"), variant).into());
+            // Points the error at `span`, a slice of the current line
+            let err = |variant, span: &str, msg: &dyn Display| {
+                Err(ShaderError::from((msg, variant)).with_label(Label::span(
+                    path,
+                    line_number,
+                    &line,
+                    span,
+                )))
+            };
+
+            // Inside of skipped blocks, only nesting is kept track of
+            if !conditions.is_active()
+                && !matches!(parts[0], "if" | "ifdef" | "ifndef" | "else" | "endif")
+            {
+                line.clear();
+                continue;
+            }
 
             match parts[0] {
                 "include" => {
                     if parts.len() != 2 {
-                        println!("[ERROR] {path:?}: {parts:?}");
                         return err(
                             ShaderErrorVariant::PPD,
+                            parts[0],
                             &"include directive takes exactly one path argument",
                         );
                     }
 
-                    // --- Path resolution
-                    let sys_workdir =
-                        std::env::current_dir().expect("System working directory should be valid");
+                    // `"path"` is looked up next to the including file first, `<path>` isn't
+                    let quoted = parts[1]
+                        .strip_prefix('"')
+                        .and_then(|path| path.strip_suffix('"'));
+                    let bracketed = parts[1]
+                        .strip_prefix('<')
+                        .and_then(|path| path.strip_suffix('>'));
+                    let (provided_path, quoted) = match (quoted, bracketed) {
+                        (Some(path), _) => (path, true),
+                        (_, Some(path)) => (path, false),
+                        _ => {
+                            return err(
+                                ShaderErrorVariant::PPD,
+                                parts[1],
+                                &"include path should be surrounded by double quotes or angle brackets",
+                            );
+                        }
+                    };
 
-                    let workdir = path
-                        .parent()
-                        .map(|p| sys_workdir.join(p))
-                        .unwrap_or(sys_workdir);
+                    // --- Path resolution
+                    let workdir = canon_path.parent().unwrap_or(Path::new(""));
 
-                    let mut provided_path = parts[1][1..(parts[1].len() - 1)].to_string();
+                    let mut provided_path = provided_path.to_string();
                     // Add .wgsl extension if it was omitted
                     if provided_path.len() < 5
                         || &provided_path[provided_path.len() - 5..] != ".wgsl"
                     {
                         provided_path.push_str(".wgsl");
                     }
-                    let include_path = workdir.join(provided_path);
-                    let include_path = include_path.canonicalize().unwrap_or_else(|_| {
-                        panic!(
-                            "Provided path should be canonicalizable: `{}`",
-                            include_path.to_str().unwrap()
-                        )
-                    });
+
+                    let candidates = self.includes.candidates(&provided_path, quoted, workdir);
+                    let mut include_path = Err(std::io::ErrorKind::NotFound.into());
+                    for candidate in candidates.iter() {
+                        include_path = source.canonicalize(candidate);
+                        if include_path.is_ok() {
+                            break;
+                        }
+                    }
+                    let include_path = match include_path {
+                        Ok(include_path) => include_path,
+                        Err(io_err) => {
+                            let mut searched = Vec::new();
+                            for candidate in candidates.iter() {
+                                let candidate = format!("`{}`", display_path(candidate).display());
+                                if !searched.contains(&candidate) {
+                                    searched.push(candidate);
+                                }
+                            }
+
+                            return err(
+                                ShaderErrorVariant::IO(io_err),
+                                parts[1],
+                                &format!("Included file not found: `{provided_path}`"),
+                            )
+                            .map_err(|err| {
+                                let err =
+                                    err.with_note(format!("searched {}", searched.join(", ")));
+                                match candidates
+                                    .iter()
+                                    .find_map(|candidate| available_shaders(source, candidate))
+                                {
+                                    Some(note) => err.with_note(note),
+                                    None => err,
+                                }
+                            });
+                        }
+                    };
+
+                    if let Some(start) = self
+                        .include_stack
+                        .iter()
+                        .position(|(path, _)| path == &include_path)
+                    {
+                        let mut includes = self.include_stack.split_off(start);
+                        if let Some((_, line)) = includes.last_mut() {
+                            *line = line_number;
+                        }
+
+                        let cycle = IncludeCycle(
+                            includes
+                                .into_iter()
+                                .map(|(path, line)| (display_path(&path), line))
+                                .collect(),
+                        );
+                        return Err(ShaderError::from(ShaderErrorVariant::IncludeCycle(cycle))
+                            .with_label(
+                                Label::span(path, line_number, &line, parts[1])
+                                    .with_message("file includes itself"),
+                            ));
+                    }
 
                     if let Some(node) = self.nodes.get(include_path.as_path()) {
                         deps.push(node.clone());
@@ -83,40 +327,148 @@ impl ShaderGraph {
                         continue;
                     }
 
-                    let include_node = std::fs::read_to_string(include_path.as_path())
-                        .map_err(ShaderError::from)
-                        .and_then(|code| self.try_add_node(include_path.as_path()))?;
+                    if let Some((_, line)) = self.include_stack.last_mut() {
+                        *line = line_number;
+                    }
+
+                    let include_node = self.try_add_node(source, include_path.as_path())?;
 
                     deps.push(include_node);
                 }
-                _ => {
+                "define" => {
+                    if parts.len() < 2 {
+                        return err(
+                            ShaderErrorVariant::PPD,
+                            parts[0],
+                            &"define directive takes a symbol name and an optional value",
+                        );
+                    }
+
+                    self.defines
+                        .insert(parts[1].to_owned(), parts[2..].join(" "));
+                }
+                "undef" => {
+                    if parts.len() != 2 {
+                        return err(
+                            ShaderErrorVariant::PPD,
+                            parts[0],
+                            &"undef directive takes exactly one symbol name",
+                        );
+                    }
+
+                    self.defines.remove(parts[1]);
+                }
+                directive @ ("ifdef" | "ifndef") => {
+                    if parts.len() != 2 {
+                        return err(
+                            ShaderErrorVariant::PPD,
+                            directive,
+                            &format!("{directive} directive takes exactly one symbol name"),
+                        );
+                    }
+
+                    let defined = self.defines.contains_key(parts[1]);
+                    conditions.push(defined == (directive == "ifdef"));
+                }
+                "if" => {
+                    if parts.len() < 2 {
+                        return err(
+                            ShaderErrorVariant::PPD,
+                            parts[0],
+                            &"if directive takes an expression",
+                        );
+                    }
+
+                    // Expressions of skipped blocks may rely on symbols that aren't defined
+                    let condition = if conditions.is_active() {
+                        match preprocessor::evaluate(&parts[1..].join(" "), &self.defines) {
+                            Ok(value) => value != 0,
+                            Err(msg) => {
+                                let expr = line[line.find(parts[1]).unwrap_or(0)..].trim_end();
+                                return err(ShaderErrorVariant::PPD, expr, &msg);
+                            }
+                        }
+                    } else {
+                        false
+                    };
+
+                    conditions.push(condition);
+                }
+                "else" => {
+                    if let Err(msg) = conditions.flip() {
+                        return err(ShaderErrorVariant::PPD, parts[0], &msg);
+                    }
+                }
+                "endif" => {
+                    if let Err(msg) = conditions.pop() {
+                        return err(ShaderErrorVariant::PPD, parts[0], &msg);
+                    }
+                }
+                directive => {
                     return err(
                         ShaderErrorVariant::PPD,
-                        &format!("Unrecognized preprocessor directive: `{:?}`", parts[0]),
+                        directive,
+                        &format!("Unrecognized preprocessor directive `{directive}`"),
                     )
                 }
             }
 
             line.clear();
-            line_number += 1;
+        }
+
+        if !conditions.is_closed() {
+            return Err(ShaderError::from((
+                "Missing endif at end of file",
+                ShaderErrorVariant::PPD,
+            ))
+            .with_label(Label::file(path)));
         }
 
         let node = Rc::new(ShaderGraphNode {
             deps,
             code,
+            lines,
             path: path.to_path_buf(),
         });
         self.nodes.insert(canon_path, node.clone());
+        self.include_stack.pop();
 
         Ok(node)
     }
 
     pub fn try_from_final(path: &Path) -> Result<Self, ShaderError> {
+        Self::try_from_final_with_defines(path, Defines::new())
+    }
+
+    /// Builds the graph with `defines` set before the final file is processed, rooting
+    /// includes at the folder of the final file.
+    ///
+    /// Files are only included once, so the symbols defined when a file is first included
+    /// decide which of its blocks make it into the graph.
+    pub fn try_from_final_with_defines(path: &Path, defines: Defines) -> Result<Self, ShaderError> {
+        let includes = IncludePaths {
+            root: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            search_paths: Vec::new(),
+        };
+        Self::try_from_final_with_source(path, defines, includes, &shader_source::default_source())
+    }
+
+    /// Builds the graph looking up includes in `includes` and reading every file from
+    /// `source` rather than from disk.
+    pub fn try_from_final_with_source(
+        path: &Path,
+        defines: Defines,
+        includes: IncludePaths,
+        source: &dyn ShaderSource,
+    ) -> Result<Self, ShaderError> {
         let mut graph = Self {
             nodes: HashMap::new(),
+            defines,
+            include_stack: Vec::new(),
+            includes,
         };
 
-        graph.try_add_node(path)?;
+        graph.try_add_node(source, path)?;
 
         Ok(graph)
     }
@@ -125,20 +477,21 @@ impl ShaderGraph {
         &self,
         node: &'n Rc<ShaderGraphNode>,
         visited: &mut Vec<&'n Rc<ShaderGraphNode>>,
-        target_buf: &mut String,
+        target: &mut FinishedShader,
     ) {
         visited.push(node);
 
         for dep in node.deps.iter() {
             if !visited.iter().any(|n| Rc::ptr_eq(dep, n)) {
-                self.finish_dfs(dep, visited, target_buf);
+                self.finish_dfs(dep, visited, target);
             }
         }
 
-        target_buf.push_str(node.code.as_str());
+        target.code.push_str(node.code.as_str());
+        target.source_map.push_node(node);
     }
 
-    pub fn finish(&self) -> Result<String, ShaderError> {
+    pub fn finish(&self) -> Result<FinishedShader, ShaderError> {
         // Find last node, i.e. the only node without any dependent
         let mut last = None;
         for node in self.nodes.values() {
@@ -152,13 +505,13 @@ impl ShaderGraph {
         }
 
         let Some(last) = last else {
-            return Err(ShaderError {
-                msg: Some("Final file not found (maybe an include loop?)".to_owned()),
-                variant: ShaderErrorVariant::PPD,
-            });
+            return Err(("Final file not found", ShaderErrorVariant::PPD).into());
         };
 
-        let mut shader = String::new();
+        let mut shader = FinishedShader {
+            code: String::new(),
+            source_map: SourceMap::default(),
+        };
         self.finish_dfs(last, &mut vec![], &mut shader);
         Ok(shader)
     }
@@ -179,8 +532,17 @@ impl ShaderGraph {
         last
     }
 
+    /// Canonical paths of the files of the graph, leaving out the ones only in memory.
     pub fn paths(&self) -> impl Iterator<Item = &Path> {
-        self.nodes.keys().map(PathBuf::as_path)
+        self.nodes
+            .keys()
+            .map(PathBuf::as_path)
+            .filter(|path| !shader_source::is_virtual(path))
+    }
+
+    /// Whether the file at the canonical `path` is part of this graph's include closure.
+    pub fn depends_on(&self, path: &Path) -> bool {
+        self.nodes.contains_key(path)
     }
 }
 
@@ -188,6 +550,21 @@ impl ShaderGraph {
 pub struct ShaderError {
     pub msg: Option<String>,
     pub variant: ShaderErrorVariant,
+    /// Where the error happened, if known
+    pub label: Option<Box<Label>>,
+    pub notes: Vec<String>,
+}
+
+impl ShaderError {
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.label = Some(Box::new(label));
+        self
+    }
+
+    pub fn with_note(mut self, note: impl ToString) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
 }
 
 impl Display for ShaderError {
@@ -208,6 +585,8 @@ impl<S: ToString, E: Into<ShaderErrorVariant>> From<(S, E)> for ShaderError {
         Self {
             msg: Some(msg.to_string()),
             variant: err.into(),
+            label: None,
+            notes: Vec::new(),
         }
     }
 }
@@ -217,6 +596,8 @@ impl<E: Into<ShaderErrorVariant>> From<E> for ShaderError {
         Self {
             msg: None,
             variant: err.into(),
+            label: None,
+            notes: Vec::new(),
         }
     }
 }
@@ -231,73 +612,340 @@ pub enum ShaderErrorVariant {
     IO(#[from] std::io::Error),
     #[error("PreProcessorDirectiveError")]
     /// Error when processing preprocessor directives
+    #[allow(clippy::upper_case_acronyms)]
     PPD,
+    #[error("IncludeCycle: {0}")]
+    /// Error when a file ends up including itself, directly or through other files
+    IncludeCycle(IncludeCycle),
+}
+
+/// Files forming an include cycle, each with the line of its include of the next one.
+/// The last file includes the first one.
+#[derive(Debug)]
+pub struct IncludeCycle(pub Vec<(PathBuf, u32)>);
+
+impl Display for IncludeCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (path, line) in self.0.iter() {
+            write!(f, "{}:{line} -> ", path.display())?;
+        }
+
+        match self.0.first() {
+            Some((path, _)) => write!(f, "{}", path.display()),
+            None => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::test::{run_test, Test};
+    use crate::{
+        shader_source::MemorySource,
+        test::{run_test, Test},
+    };
     use indoc::indoc;
 
     #[test]
     fn shader_graph() {
         run_test(Test::new(
+            || {},
             || {
-                if !Path::new("./.test_dir").is_dir() {
-                    std::fs::create_dir("./.test_dir")
-                        .expect(".test_dir/ should be successfully created");
-                }
+                let source = MemorySource::new()
+                    .with(
+                        "shaders/main.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% include "bar"
+                            //% include "foo"
+
+                            fn main() {}
+                        "#},
+                    )
+                    .with(
+                        "shaders/foo.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% include "bar"
+                            
+                            fn foo() {}
+                        "#},
+                    )
+                    .with(
+                        "shaders/bar.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            fn bar() {}
+                        "#},
+                    );
+
+                let graph = ShaderGraph::try_from_final_with_source(
+                    Path::new("shaders/main.wgsl"),
+                    Defines::new(),
+                    IncludePaths::default(),
+                    &source,
+                )
+                .expect("Graph should be properly created");
+
+                let FinishedShader { code, source_map } = graph
+                    .finish()
+                    .expect("Final code should be created properly");
 
-                let main = indoc! {/*wgsl*/ r#"
-                    //% include "bar"
-                    //% include "foo"
+                let foo = source_map
+                    .lookup(3)
+                    .expect("Line 3 should come from an included file");
+                assert!(foo.path.ends_with("foo.wgsl"));
+                assert_eq!(foo.line, 3);
 
-                    fn main() {}
-                "#};
+                let main = source_map
+                    .lookup(5)
+                    .expect("Line 5 should come from the final file");
+                assert!(main.path.ends_with("main.wgsl"));
+                assert_eq!(main.line, 4);
 
-                let foo = indoc! {/*wgsl*/ r#"
-                    //% include "bar"
-                    
-                    fn foo() {}
-                "#};
+                assert!(source_map.lookup(6).is_none());
 
-                let bar = indoc! {/*wgsl*/ r#"
-                    fn bar() {}
-                "#};
+                assert_eq!(
+                    code.trim(),
+                    indoc! {r#"
+                        fn bar() {}
+                        
+                        fn foo() {}
+
+                        fn main() {}
+                    "#}
+                    .trim()
+                );
 
-                std::fs::write(".test_dir/main.wgsl", main)
-                    .and_then(|_| std::fs::write(".test_dir/foo.wgsl", foo))
-                    .and_then(|_| std::fs::write(".test_dir/bar.wgsl", bar))
-                    .expect("Wgsl test files should be written to .test_dir");
+                // Missing includes point at the shaders that could have been meant
+                let source = source.with("shaders/typo.wgsl", r#"//% include "baz""#);
+                let err = ShaderGraph::try_from_final_with_source(
+                    Path::new("shaders/typo.wgsl"),
+                    Defines::new(),
+                    IncludePaths::default(),
+                    &source,
+                )
+                .err()
+                .expect("Missing include should fail");
+                assert_eq!(
+                    err.notes,
+                    [
+                        "searched `shaders/baz.wgsl`, `baz.wgsl`",
+                        "shaders in `shaders`: `bar`, `foo`, `main`, `typo`"
+                    ]
+                );
             },
+            || {},
+        ))
+    }
+
+    #[test]
+    fn depends_on() {
+        run_test(Test::new(
+            || {},
             || {
-                let graph = ShaderGraph::try_from_final(Path::new(".test_dir/main.wgsl"))
-                    .expect("Graph should be properly created");
+                let source = MemorySource::new()
+                    .with("main.wgsl", "//% include \"foo\"\n")
+                    .with("foo.wgsl", "//% include \"bar\"\n")
+                    .with("bar.wgsl", "fn bar() {}\n")
+                    .with("unrelated.wgsl", "fn unrelated() {}\n");
+
+                let graph = ShaderGraph::try_from_final_with_source(
+                    Path::new("main.wgsl"),
+                    Defines::new(),
+                    IncludePaths::default(),
+                    &source,
+                )
+                .expect("Graph should be properly created");
+
+                // Transitive includes are part of the closure, other files aren't
+                assert!(graph.depends_on(Path::new("main.wgsl")));
+                assert!(graph.depends_on(Path::new("foo.wgsl")));
+                assert!(graph.depends_on(Path::new("bar.wgsl")));
+                assert!(!graph.depends_on(Path::new("unrelated.wgsl")));
+            },
+            || {},
+        ))
+    }
 
-                let code = graph
+    #[test]
+    fn include_paths() {
+        run_test(Test::new(
+            || {},
+            || {
+                let source = MemorySource::new()
+                    .with(
+                        "assets/passes/main.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% include "@/lib/common"
+                            //% include <noise>
+                            //% include "helper"
+                        "#},
+                    )
+                    .with("assets/passes/bracketed.wgsl", "//% include <helper>\n")
+                    .with("assets/passes/helper.wgsl", "fn helper() {}\n")
+                    .with("assets/lib/common.wgsl", "fn common() {}\n")
+                    .with("assets/extra/noise.wgsl", "fn noise() {}\n")
+                    .with("assets/noise.wgsl", "fn shadowed() {}\n");
+                let includes = IncludePaths::new(Path::new("assets"), &["extra".to_owned()]);
+
+                let graph = ShaderGraph::try_from_final_with_source(
+                    Path::new("assets/passes/main.wgsl"),
+                    Defines::new(),
+                    includes.clone(),
+                    &source,
+                )
+                .expect("Graph should be properly created");
+                let FinishedShader { code, .. } = graph
+                    .finish()
+                    .expect("Final code should be created properly");
+                assert_eq!(code, "fn common() {}\nfn noise() {}\nfn helper() {}\n");
+
+                // Bracketed includes aren't looked up next to the including file
+                let err = ShaderGraph::try_from_final_with_source(
+                    Path::new("assets/passes/bracketed.wgsl"),
+                    Defines::new(),
+                    includes,
+                    &source,
+                )
+                .err()
+                .expect("Bracketed include should only be looked up in the search paths");
+                assert_eq!(
+                    err.notes[0],
+                    "searched `assets/extra/helper.wgsl`, `assets/helper.wgsl`"
+                );
+            },
+            || {},
+        ))
+    }
+
+    #[test]
+    fn conditional_compilation() {
+        run_test(Test::new(
+            || {},
+            || {
+                let source = MemorySource::new()
+                    .with(
+                        "main.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% define FANCY
+                            //% include "lib"
+
+                            //% if QUALITY >= 2 && !defined(LOW_POWER)
+                            const OCTAVES: i32 = 8;
+                            //% else
+                            const OCTAVES: i32 = 4;
+                            //% endif
+                        "#},
+                    )
+                    .with(
+                        "lib.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% ifdef FANCY
+                            fn fancy() {}
+                            //% ifndef PLAIN
+                            fn not_plain() {}
+                            //% endif
+                            //% else
+                            //% include "missing"
+                            fn plain() {}
+                            //% endif
+                        "#},
+                    )
+                    .with(
+                        "unclosed.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% ifdef FANCY
+                            fn fancy() {}
+                        "#},
+                    );
+
+                let graph = ShaderGraph::try_from_final_with_source(
+                    Path::new("main.wgsl"),
+                    Defines::from([("QUALITY".to_owned(), "2".to_owned())]),
+                    IncludePaths::default(),
+                    &source,
+                )
+                .expect("Graph should be properly created");
+
+                let FinishedShader { code, .. } = graph
                     .finish()
                     .expect("Final code should be created properly");
 
                 assert_eq!(
                     code.trim(),
                     indoc! {r#"
-                        fn bar() {}
-                        
-                        fn foo() {}
+                        fn fancy() {}
+                        fn not_plain() {}
 
-                        fn main() {}
+                        const OCTAVES: i32 = 8;
                     "#}
                     .trim()
                 );
+
+                assert!(ShaderGraph::try_from_final_with_source(
+                    Path::new("unclosed.wgsl"),
+                    Defines::new(),
+                    IncludePaths::default(),
+                    &source,
+                )
+                .is_err());
             },
+            || {},
+        ))
+    }
+
+    #[test]
+    fn include_cycles() {
+        run_test(Test::new(
+            || {},
             || {
-                std::fs::remove_file(".test_dir/main.wgsl")
-                    .and_then(|_| std::fs::remove_file(".test_dir/foo.wgsl"))
-                    .and_then(|_| std::fs::remove_file(".test_dir/bar.wgsl"))
-                    .expect("Wgsl test files should be deleted");
-                std::fs::remove_dir(".test_dir").expect(".test_dir should be removed");
+                let source = MemorySource::new()
+                    .with(
+                        "direct.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            fn direct() {}
+                            //% include "direct"
+                        "#},
+                    )
+                    .with(
+                        "foo.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% include "bar"
+                        "#},
+                    )
+                    .with(
+                        "bar.wgsl",
+                        indoc! {/*wgsl*/ r#"
+                            //% define BAR
+
+                            //% include "foo"
+                        "#},
+                    );
+
+                let cycle = |path| match ShaderGraph::try_from_final_with_source(
+                    Path::new(path),
+                    Defines::new(),
+                    IncludePaths::default(),
+                    &source,
+                ) {
+                    Err(ShaderError {
+                        variant: ShaderErrorVariant::IncludeCycle(IncludeCycle(includes)),
+                        ..
+                    }) => includes
+                        .into_iter()
+                        .map(|(path, line)| {
+                            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+                            (name, line)
+                        })
+                        .collect::<Vec<_>>(),
+                    _ => panic!("Include cycle should be detected in {path}"),
+                };
+
+                assert_eq!(cycle("direct.wgsl"), [("direct".to_owned(), 2)]);
+                assert_eq!(
+                    cycle("foo.wgsl"),
+                    [("foo".to_owned(), 1), ("bar".to_owned(), 3)]
+                );
             },
+            || {},
         ))
     }
 }
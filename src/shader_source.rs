@@ -0,0 +1,254 @@
+use crate::{generated, std_lib};
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+/// Where a shader graph reads its files from.
+pub trait ShaderSource {
+    /// Content of the file at the canonical `path`.
+    fn read(&self, path: &Path) -> io::Result<String>;
+
+    /// Canonical form of `path`, which fails if there is no file or folder there.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Canonical paths of the files and folders in the folder at the canonical `path`.
+    fn list(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+}
+
+/// Files on disk, relative paths being relative to the working directory.
+pub struct FileSystem;
+
+impl ShaderSource for FileSystem {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn list(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+}
+
+/// Files kept in memory, by path.
+///
+/// Paths are canonicalized by resolving `.` and `..` only, folders existing as long as they
+/// contain a file.
+#[derive(Debug, Default, Clone)]
+pub struct MemorySource {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, path: impl AsRef<Path>, content: impl ToString) -> Self {
+        self.insert(path, content);
+        self
+    }
+
+    pub fn insert(&mut self, path: impl AsRef<Path>, content: impl ToString) {
+        self.files
+            .insert(normalize(path.as_ref()), content.to_string());
+    }
+}
+
+/// Resolves `.` and `..` in `path` without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if !matches!(
+                    normalized.components().next_back(),
+                    None | Some(Component::ParentDir)
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+impl ShaderSource for MemorySource {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.files
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = normalize(path);
+        match self.files.keys().any(|file| file.starts_with(&path)) {
+            true => Ok(path),
+            false => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn list(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let path = self.canonicalize(path)?;
+        let mut entries = self
+            .files
+            .keys()
+            .filter_map(|file| {
+                let child = file.strip_prefix(&path).ok()?.components().next()?;
+                Some(path.join(child))
+            })
+            .collect::<Vec<_>>();
+        entries.dedup();
+        Ok(entries)
+    }
+}
+
+/// Sources stacked on top of each other, the first one having a file shadowing the others.
+#[derive(Default)]
+pub struct LayeredSource {
+    layers: Vec<Box<dyn ShaderSource>>,
+}
+
+impl LayeredSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a layer below the existing ones.
+    pub fn with(mut self, layer: impl ShaderSource + 'static) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+}
+
+impl ShaderSource for LayeredSource {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        first_of(&self.layers, |layer| layer.read(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        first_of(&self.layers, |layer| layer.canonicalize(path))
+    }
+
+    /// Entries of the folder in every layer that has it.
+    fn list(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = Vec::new();
+        let mut found = false;
+        for layer in self.layers.iter() {
+            if let Ok(layer_entries) = layer.list(path) {
+                found = true;
+                for entry in layer_entries {
+                    if !entries.contains(&entry) {
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+
+        match found {
+            true => Ok(entries),
+            false => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+/// Result of the first layer `f` succeeds on, or the error of the last one.
+fn first_of<T>(
+    layers: &[Box<dyn ShaderSource>],
+    f: impl Fn(&dyn ShaderSource) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut result = Err(io::ErrorKind::NotFound.into());
+    for layer in layers {
+        result = f(layer.as_ref());
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// Source shaders of the render graph are read from: generated WGSL, files on disk, then the
+/// standard library embedded in the binary.
+pub fn default_source() -> LayeredSource {
+    LayeredSource::new()
+        .with(generated::source())
+        .with(FileSystem)
+        .with(std_lib::source())
+}
+
+/// Whether `path` belongs to a file that only exists in memory, under a root such as
+/// `<generated>` or `<std>`, so that there is nothing to watch.
+pub fn is_virtual(path: &Path) -> bool {
+    path.components().next().is_some_and(|component| {
+        let component = component.as_os_str().to_string_lossy();
+        component.starts_with('<') && component.ends_with('>')
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    #[test]
+    fn shader_sources() {
+        run_test(Test::new(
+            || {},
+            || {
+                let memory = MemorySource::new()
+                    .with("lib/sdf.wgsl", "fn sdf() {}")
+                    .with("lib/noise/perlin.wgsl", "fn perlin() {}")
+                    .with("main.wgsl", "fn main() {}");
+
+                assert_eq!(
+                    memory
+                        .canonicalize(Path::new("lib/noise/../sdf.wgsl"))
+                        .expect("File should exist"),
+                    Path::new("lib/sdf.wgsl")
+                );
+                assert!(memory.canonicalize(Path::new("lib/missing.wgsl")).is_err());
+                assert_eq!(
+                    memory
+                        .list(Path::new("./lib"))
+                        .expect("Folder should exist"),
+                    [Path::new("lib/noise"), Path::new("lib/sdf.wgsl")]
+                );
+
+                // Upper layers shadow lower ones
+                let layered = LayeredSource::new()
+                    .with(MemorySource::new().with("main.wgsl", "fn shadowed() {}"))
+                    .with(memory);
+                assert_eq!(
+                    layered
+                        .read(Path::new("main.wgsl"))
+                        .expect("File should exist"),
+                    "fn shadowed() {}"
+                );
+                assert_eq!(
+                    layered
+                        .read(Path::new("lib/sdf.wgsl"))
+                        .expect("File should exist"),
+                    "fn sdf() {}"
+                );
+                assert_eq!(
+                    layered.list(Path::new("")).expect("Root should exist"),
+                    [Path::new("main.wgsl"), Path::new("lib")]
+                );
+
+                assert!(is_virtual(Path::new("<generated>/globals.wgsl")));
+                assert!(!is_virtual(Path::new("lib/sdf.wgsl")));
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,668 @@
+use crate::{globals::Globals, Config};
+
+use std::path::{Path, PathBuf};
+
+use {
+    anyhow::{anyhow, bail, Context},
+    serde::Deserialize,
+};
+
+/// Folder of the assets imported shaders are written to, as `<folder>/<name>/<pass>.wgsl`.
+pub const SHADERTOY_FOLDER: &str = "shadertoy";
+
+/// Either `{"Shader": {..}}` as returned by the API, a list of shaders as exported from the
+/// site, or a shader on its own.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Export {
+    Api {
+        #[serde(rename = "Shader")]
+        shader: Shader,
+    },
+    List(Vec<Shader>),
+    Single(Shader),
+}
+
+#[derive(Deserialize)]
+struct Shader {
+    info: Info,
+    renderpass: Vec<RenderPass>,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct RenderPass {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    code: String,
+    #[serde(default)]
+    inputs: Vec<RenderPassInput>,
+    #[serde(default)]
+    outputs: Vec<RenderPassOutput>,
+}
+
+#[derive(Deserialize)]
+struct RenderPassInput {
+    id: Id,
+    #[serde(default)]
+    src: String,
+    ctype: String,
+    channel: u32,
+}
+
+#[derive(Deserialize)]
+struct RenderPassOutput {
+    id: Id,
+}
+
+/// Ids are strings in recent exports and numbers in older ones.
+#[derive(Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+enum Id {
+    String(String),
+    Number(u64),
+}
+
+/// A Shadertoy shader converted to WGSL, along with the config running it.
+#[derive(Debug)]
+pub struct ShadertoyImport {
+    /// Name of the folder the shaders go in
+    pub name: String,
+    /// WGSL of every pass, by file stem
+    pub shaders: Vec<(String, String)>,
+    /// RON of the config
+    pub config: String,
+    /// Inputs that couldn't be carried over, or need to be downloaded
+    pub warnings: Vec<String>,
+}
+
+/// Converts the GLSL passes of a Shadertoy JSON export to WGSL, named `name` or after the
+/// shader, to be written to `assets_folder`.
+///
+/// Buffers and the image become passes reading each other through `t_channelN` inputs,
+/// common code gets prepended to every one of them.
+pub fn convert(
+    json: &str,
+    name: Option<&str>,
+    assets_folder: &str,
+) -> anyhow::Result<ShadertoyImport> {
+    let shader = match serde_json::from_str(json).context("Invalid Shadertoy export")? {
+        Export::Api { shader } | Export::Single(shader) => shader,
+        Export::List(shaders) => shaders
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Shadertoy export has no shader"))?,
+    };
+
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None => slug(&shader.info.name)
+            .or_else(|| slug(&shader.info.id))
+            .unwrap_or_else(|| "shadertoy".to_owned()),
+    };
+    let globals = glsl_globals()?;
+    let common = shader
+        .renderpass
+        .iter()
+        .filter(|pass| pass.kind == "common")
+        .map(|pass| pass.code.as_str())
+        .collect::<String>();
+
+    // Buffers run in the order of the export, the image after all of them
+    let mut passes = shader
+        .renderpass
+        .iter()
+        .filter(|pass| pass.kind == "buffer")
+        .collect::<Vec<_>>();
+    let mut unsupported = Vec::new();
+    for pass in shader.renderpass.iter() {
+        match pass.kind.as_str() {
+            "buffer" | "common" | "image" => {}
+            _ => unsupported.push(format!(
+                "`{}` passes ({}) aren't supported",
+                pass.kind, pass.name
+            )),
+        }
+    }
+    passes.extend(shader.renderpass.iter().filter(|pass| pass.kind == "image"));
+    if !passes.last().is_some_and(|pass| pass.kind == "image") {
+        bail!("Shadertoy export has no image pass");
+    }
+
+    let mut import = ShadertoyImport {
+        name,
+        shaders: Vec::new(),
+        config: String::new(),
+        warnings: unsupported,
+    };
+    let mut pass_configs = Vec::new();
+    let mut errors = Vec::new();
+    for (index, pass) in passes.iter().enumerate() {
+        let pass_name = slug(&pass.name).unwrap_or_else(|| format!("pass_{index}"));
+
+        let mut inputs = Vec::new();
+        for input in pass.inputs.iter() {
+            let source = match input.ctype.as_str() {
+                "buffer" => {
+                    let Some(writer) = passes
+                        .iter()
+                        .position(|other| other.outputs.iter().any(|output| output.id == input.id))
+                    else {
+                        import.warnings.push(format!(
+                            "{}: iChannel{} reads a buffer that isn't in the export",
+                            pass.name, input.channel
+                        ));
+                        continue;
+                    };
+                    let writer_name =
+                        slug(&passes[writer].name).unwrap_or_else(|| format!("pass_{writer}"));
+
+                    // Buffers that didn't run yet this frame are read as of the previous one
+                    match writer < index {
+                        true => format!("Output({})", ron::to_string(&writer_name)?),
+                        false => format!("Previous({})", ron::to_string(&writer_name)?),
+                    }
+                }
+                // Shadertoy flips textures vertically by default, images don't get flipped here
+                "texture" => {
+                    let file = input.src.rsplit('/').next().unwrap_or_default();
+                    let path = format!("{SHADERTOY_FOLDER}/{}/{file}", import.name);
+                    import.warnings.push(format!(
+                        "{}: download https://www.shadertoy.com{} as '{path}' in the assets folder",
+                        pass.name, input.src
+                    ));
+                    format!("Image({})", ron::to_string(&path)?)
+                }
+                ctype => {
+                    import.warnings.push(format!(
+                        "{}: iChannel{} is a `{ctype}`, which isn't supported",
+                        pass.name, input.channel
+                    ));
+                    continue;
+                }
+            };
+            inputs.push((input.channel, source));
+        }
+        inputs.sort();
+
+        let channels = inputs
+            .iter()
+            .map(|(channel, ..)| *channel)
+            .collect::<Vec<_>>();
+        let source = glsl_source(
+            &globals,
+            &common,
+            &pass.code,
+            &channels,
+            pass.kind == "image",
+        );
+        match to_wgsl(&source, &channels) {
+            Ok(wgsl) => {
+                let header = format!(
+                    "// Converted from the `{}` pass of Shadertoy {}\n\
+                     //% include <std/utils/gen_triangle_vs>\n\n",
+                    pass.name,
+                    match shader.info.id.is_empty() {
+                        true => format!("\"{}\"", shader.info.name),
+                        false => format!("https://www.shadertoy.com/view/{}", shader.info.id),
+                    }
+                );
+                import.shaders.push((pass_name.clone(), header + &wgsl));
+            }
+            Err(err) => errors.push(format!("{}:\n{err}", pass.name)),
+        }
+
+        pass_configs.push((pass_name, inputs));
+    }
+    if !errors.is_empty() {
+        bail!("Failed to convert Shadertoy passes\n{}", errors.join("\n"));
+    }
+
+    import.config = config_source(
+        &shader.info.name,
+        &import.name,
+        assets_folder,
+        &pass_configs,
+    )?;
+    Ok(import)
+}
+
+/// Writes the shaders of `import` to the assets folder of `config`, and the config running
+/// them next to them, returning its path.
+pub fn write_import(config: &Config, import: &ShadertoyImport) -> anyhow::Result<PathBuf> {
+    let folder = config
+        .assets_folder()
+        .join(SHADERTOY_FOLDER)
+        .join(&import.name);
+    std::fs::create_dir_all(&folder)
+        .with_context(|| format!("Failed to create '{}'", folder.display()))?;
+
+    for (name, wgsl) in import.shaders.iter() {
+        let path = folder.join(name).with_extension("wgsl");
+        std::fs::write(&path, wgsl)
+            .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    }
+
+    let path = folder.join("config.ron");
+    std::fs::write(&path, &import.config)
+        .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    Ok(path)
+}
+
+/// Imports the Shadertoy export at `path` into the assets folder of `config`.
+pub fn import_shadertoy(config: &Config, path: &Path, name: Option<&str>) -> anyhow::Result<()> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read '{}'", path.display()))?;
+    let import = convert(&json, name, &config.assets_folder)?;
+
+    for warning in import.warnings.iter() {
+        eprintln!("warning: {warning}");
+    }
+    let config_path = write_import(config, &import)?;
+    println!(
+        "Imported {} passes, replace {} with '{}' to run them",
+        import.shaders.len(),
+        config.path.display(),
+        config_path.display()
+    );
+
+    Ok(())
+}
+
+/// Lowercase identifier made of the alphanumeric characters of `name`, e.g. `buffer_a` for
+/// `Buffer A`.
+fn slug(name: &str) -> Option<String> {
+    let slug = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("_");
+
+    (!slug.is_empty()).then_some(slug)
+}
+
+/// Members of the GLSL uniform block of `Globals`, generated from its WGSL so that they can't
+/// drift apart.
+fn glsl_globals() -> anyhow::Result<String> {
+    let module = naga::front::wgsl::parse_str(Globals::WGSL)?;
+    let members = module
+        .types
+        .iter()
+        .find_map(|(_, ty)| match &ty.inner {
+            naga::TypeInner::Struct { members, .. } if ty.name.as_deref() == Some("Globals") => {
+                Some(members)
+            }
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("Globals should be a struct"))?;
+
+    members
+        .iter()
+        .map(|member| {
+            let name = member.name.as_deref().unwrap_or_default();
+            let (ty, array) = glsl_type(&module, member.ty)?;
+            Ok(format!("    {ty} {name}{array};\n"))
+        })
+        .collect()
+}
+
+/// GLSL type of the WGSL type `ty` and its array suffix, e.g. `vec4` and `[4]` for
+/// `array<vec4f, 4>`.
+fn glsl_type(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+) -> anyhow::Result<(String, String)> {
+    // Name of the scalar type, and prefix of its vectors
+    let scalar = |scalar: &naga::Scalar| match (scalar.kind, scalar.width) {
+        (naga::ScalarKind::Sint, 4) => Ok(("int", "i")),
+        (naga::ScalarKind::Uint, 4) => Ok(("uint", "u")),
+        (naga::ScalarKind::Float, 4) => Ok(("float", "")),
+        (naga::ScalarKind::Bool, _) => Ok(("bool", "b")),
+        _ => Err(anyhow!("No GLSL equivalent for {scalar:?}")),
+    };
+
+    match &module.types[ty].inner {
+        naga::TypeInner::Scalar(inner) => Ok((scalar(inner)?.0.to_owned(), String::new())),
+        naga::TypeInner::Vector {
+            size,
+            scalar: inner,
+        } => Ok((
+            format!("{}vec{}", scalar(inner)?.1, *size as u8),
+            String::new(),
+        )),
+        naga::TypeInner::Array {
+            base,
+            size: naga::ArraySize::Constant(size),
+            ..
+        } => {
+            let (ty, array) = glsl_type(module, *base)?;
+            Ok((ty, format!("[{size}]{array}")))
+        }
+        inner => Err(anyhow!("No GLSL equivalent for {inner:?}")),
+    }
+}
+
+/// Fragment shader calling `mainImage`, with Shadertoy's uniforms mapped onto the `globals`
+/// members and the textures of `channels`.
+///
+/// Shadertoy's origin is the bottom left corner: the image gets flipped, while buffers are
+/// stored upside down so that sampling them with Shadertoy's coordinates stays correct.
+fn glsl_source(
+    globals: &str,
+    common: &str,
+    code: &str,
+    channels: &[u32],
+    is_image: bool,
+) -> String {
+    let mut source = format!(
+        "#version 450
+
+layout(set = 0, binding = 0) uniform Globals {{
+{globals}}} globals;
+"
+    );
+    source += "
+vec4 shadertoy_mouse() {
+    vec4 mouse = globals.mouse;
+    if (mouse.z == 0.0 && mouse.w == 0.0) {
+        return vec4(0.0);
+    }
+    float height = globals.resolution.y;
+    return vec4(mouse.x, height - mouse.y, mouse.z, sign(mouse.w) * (height - abs(mouse.w)));
+}
+
+#define iResolution globals.resolution
+#define iTime globals.time
+#define iTimeDelta globals.time_delta
+#define iFrame int(globals.frame)
+#define iFrameRate globals.frame_rate
+#define iMouse shadertoy_mouse()
+#define iDate globals.date
+#define iChannelResolution globals.channel_resolution
+#define iSampleRate 44100.0
+";
+
+    for channel in channels {
+        let binding = 1 + 2 * channel;
+        source += &format!(
+            "
+layout(set = 0, binding = {binding}) uniform texture2D t_channel{channel};
+layout(set = 0, binding = {}) uniform sampler s_channel{channel};
+#define iChannel{channel} sampler2D(t_channel{channel}, s_channel{channel})
+",
+            binding + 1
+        );
+    }
+
+    source += "\n";
+    source += common;
+    source += "\n";
+    source += code;
+    source += &format!(
+        "
+
+layout(location = 0) out vec4 shadertoy_color;
+
+void main() {{
+    vec2 coord = gl_FragCoord.xy;
+    {}
+    vec4 color = vec4(0.0);
+    mainImage(color, coord);
+    shadertoy_color = {};
+}}
+",
+        match is_image {
+            true => "coord.y = iResolution.y - coord.y;",
+            false => "",
+        },
+        // The alpha of the image is ignored by Shadertoy
+        match is_image {
+            true => "vec4(color.rgb, 1.0)",
+            false => "color",
+        },
+    );
+
+    source
+}
+
+/// Converts a GLSL fragment shader to WGSL, its entry point becoming `fs_main`.
+fn to_wgsl(source: &str, channels: &[u32]) -> Result<String, String> {
+    let mut module = naga::front::glsl::Frontend::default()
+        .parse(
+            &naga::front::glsl::Options::from(naga::ShaderStage::Fragment),
+            source,
+        )
+        .map_err(|err| err.emit_to_string(source))?;
+    for entry_point in module.entry_points.iter_mut() {
+        entry_point.name = "fs_main".to_owned();
+    }
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|err| err.emit_to_string(source))?;
+
+    let wgsl =
+        naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|err| err.to_string())?;
+
+    // The writer suffixes names ending with a digit, which bindings are looked up by
+    Ok(channels.iter().fold(wgsl, |wgsl, channel| {
+        ["t", "s"].iter().fold(wgsl, |wgsl, prefix| {
+            let name = format!("{prefix}_channel{channel}");
+            rename(&wgsl, &format!("{name}_"), &name)
+        })
+    }))
+}
+
+/// Replaces the identifier `from` with `to`, leaving longer identifiers containing it as is.
+fn rename(source: &str, from: &str, to: &str) -> String {
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut renamed = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find(from) {
+        let end = start + from.len();
+        let standalone =
+            !rest[..start].ends_with(is_identifier) && !rest[end..].starts_with(is_identifier);
+
+        renamed += &rest[..start];
+        renamed += if standalone { to } else { from };
+        rest = &rest[end..];
+    }
+    renamed + rest
+}
+
+/// RON of a config running `passes`, given as their name and inputs.
+fn config_source(
+    title: &str,
+    name: &str,
+    assets_folder: &str,
+    passes: &[(String, Vec<(u32, String)>)],
+) -> anyhow::Result<String> {
+    let mut config = format!(
+        "Config (
+    // Size of Shadertoy's preview
+    window_size: (800, 450),
+    window_title: {},
+
+    fps_limit: Some(60),
+    assets_folder: {},
+    passes: [
+",
+        ron::to_string(title)?,
+        ron::to_string(assets_folder)?,
+    );
+
+    for (pass, inputs) in passes {
+        let inputs = inputs
+            .iter()
+            .map(|(channel, source)| format!("\"t_channel{channel}\": {source}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        config += &format!(
+            "        (
+            name: {},
+            shader: {},
+            inputs: {{{inputs}}},
+        ),
+",
+            ron::to_string(pass)?,
+            ron::to_string(&format!("{SHADERTOY_FOLDER}/{name}/{pass}"))?,
+        );
+    }
+
+    let output = passes
+        .last()
+        .map(|(pass, _)| pass.as_str())
+        .unwrap_or_default();
+    config += &format!(
+        "    ],\n    output: Some({}),\n)\n",
+        ron::to_string(output)?
+    );
+    Ok(config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{run_test, Test};
+
+    use std::mem::offset_of;
+
+    #[test]
+    fn convert_shadertoy() {
+        run_test(Test::new(
+            || {},
+            || {
+                let json = r#"{"Shader": {
+                    "info": {"id": "abc123", "name": "Feedback Test"},
+                    "renderpass": [
+                        {
+                            "name": "Image", "type": "image",
+                            "inputs": [{"id": "4dXGR8", "src": "/media/previz/buffer00.png", "ctype": "buffer", "channel": 0}],
+                            "outputs": [{"id": "4dfGRr", "channel": 0}],
+                            "code": "void mainImage(out vec4 fragColor, in vec2 fragCoord) { fragColor = texture(iChannel0, fragCoord / iResolution.xy) * tint(); }"
+                        },
+                        {
+                            "name": "Buffer A", "type": "buffer",
+                            "inputs": [
+                                {"id": "4dXGR8", "src": "/media/previz/buffer00.png", "ctype": "buffer", "channel": 1},
+                                {"id": "XsX3Rn", "src": "/media/a/abc.png", "ctype": "texture", "channel": 0},
+                                {"id": "4dXGRr", "src": "/presets/tex00.jpg", "ctype": "keyboard", "channel": 2}
+                            ],
+                            "outputs": [{"id": "4dXGR8", "channel": 0}],
+                            "code": "void mainImage(out vec4 fragColor, in vec2 fragCoord) { vec2 uv = fragCoord / iResolution.xy; fragColor = mix(texture(iChannel1, uv), texture(iChannel0, uv), 0.1) + iMouse.x * float(iFrame) * sin(iTime); }"
+                        },
+                        {"name": "Common", "type": "common", "code": "vec4 tint() { return vec4(1.0, 0.5, 0.5, 1.0); }"}
+                    ]
+                }}"#;
+
+                let import =
+                    convert(json, None, "assets").expect("Shadertoy export should convert");
+                assert_eq!(import.name, "feedback_test");
+                assert_eq!(
+                    import
+                        .shaders
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>(),
+                    ["buffer_a", "image"]
+                );
+                for (_, wgsl) in import.shaders.iter() {
+                    assert!(wgsl.contains("fn fs_main("));
+                    assert!(wgsl.contains("var<uniform> globals: Globals"));
+                }
+                assert!(import.shaders[0]
+                    .1
+                    .contains("var t_channel1: texture_2d<f32>"));
+
+                // The converted block has the layout of the Rust struct
+                let module = naga::front::wgsl::parse_str(&import.shaders[1].1)
+                    .expect("Converted shader should be valid WGSL");
+                let offsets = module
+                    .types
+                    .iter()
+                    .find_map(|(_, ty)| match &ty.inner {
+                        naga::TypeInner::Struct { members, .. }
+                            if ty.name.as_deref() == Some("Globals") =>
+                        {
+                            Some(members.iter().map(|member| member.offset as usize))
+                        }
+                        _ => None,
+                    })
+                    .expect("Converted shader should declare Globals")
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    offsets,
+                    [
+                        offset_of!(Globals, resolution),
+                        offset_of!(Globals, time),
+                        offset_of!(Globals, time_delta),
+                        offset_of!(Globals, frame),
+                        offset_of!(Globals, frame_rate),
+                        offset_of!(Globals, mouse_state),
+                        offset_of!(Globals, mouse),
+                        offset_of!(Globals, cursor),
+                        offset_of!(Globals, date),
+                        offset_of!(Globals, channel_resolution),
+                    ]
+                );
+                assert_eq!(import.warnings.len(), 2);
+
+                let config: Config =
+                    ron::from_str(&import.config).expect("Imported config should be valid");
+                assert_eq!(config.output.as_deref(), Some("image"));
+                let inputs = config
+                    .passes
+                    .iter()
+                    .map(|pass| (pass.name.as_str(), pass.inputs.clone()))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    inputs,
+                    [
+                        (
+                            "buffer_a",
+                            [
+                                (
+                                    "t_channel0".to_owned(),
+                                    crate::render_graph::Input::Image(
+                                        "shadertoy/feedback_test/abc.png".to_owned()
+                                    )
+                                ),
+                                (
+                                    "t_channel1".to_owned(),
+                                    crate::render_graph::Input::Previous("buffer_a".to_owned())
+                                ),
+                            ]
+                            .into()
+                        ),
+                        (
+                            "image",
+                            [(
+                                "t_channel0".to_owned(),
+                                crate::render_graph::Input::Output("buffer_a".to_owned())
+                            )]
+                            .into()
+                        ),
+                    ]
+                );
+
+                let broken = json.replace("* tint()", "* missing()");
+                assert!(convert(&broken, None, "assets").is_err());
+            },
+            || {},
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::shader_source::MemorySource;
+
+use std::path::{Path, PathBuf};
+
+mod embedded {
+    include!(concat!(env!("OUT_DIR"), "/std_lib.rs"));
+}
+
+/// Prefix of the library includes, e.g. `<std/noise/perlin>`.
+pub const INCLUDE_PREFIX: &str = "std/";
+
+/// Root of the paths of library files in shader graphs, which can't clash with real files.
+const VIRTUAL_ROOT: &str = "<std>";
+
+/// Virtual path of the library file an include refers to, whether or not it exists.
+///
+/// `include` is the path as written in the include, with its extension.
+pub fn resolve(include: &str) -> Option<PathBuf> {
+    let name = include.strip_prefix(INCLUDE_PREFIX)?;
+    Some(Path::new(VIRTUAL_ROOT).join(name))
+}
+
+/// Every file of the standard library, embedded from `std/` at compile time.
+pub fn source() -> MemorySource {
+    embedded::FILES
+        .iter()
+        .fold(MemorySource::new(), |source, (name, content)| {
+            source.with(Path::new(VIRTUAL_ROOT).join(name), content)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        preprocessor::Defines,
+        shader_graph::{IncludePaths, ShaderGraph},
+        shader_source::{LayeredSource, ShaderSource},
+        test::{run_test, Test},
+    };
+    use indoc::indoc;
+
+    #[test]
+    fn std_lib() {
+        run_test(Test::new(
+            || {},
+            || {
+                let names = embedded::FILES
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>();
+                assert!(names.contains(&"noise/perlin.wgsl"));
+                assert!(names.contains(&"utils/gen_triangle_vs.wgsl"));
+
+                let path = resolve("std/sdf.wgsl").expect("Include should be in the library");
+                assert!(source().read(&path).is_ok());
+                assert_eq!(resolve("sdf.wgsl"), None);
+
+                // Files under the root shadow the library, relative includes of which stay
+                // inside of it
+                let source = LayeredSource::new()
+                    .with(
+                        MemorySource::new()
+                            .with(
+                                "project/main.wgsl",
+                                indoc! {/*wgsl*/ r#"
+                                    //% include <std/sdf>
+                                    //% include <std/noise/perlin>
+                                "#},
+                            )
+                            .with("project/std/sdf.wgsl", "fn shadowed() {}\n"),
+                    )
+                    .with(super::source());
+                let graph = ShaderGraph::try_from_final_with_source(
+                    Path::new("project/main.wgsl"),
+                    Defines::new(),
+                    IncludePaths::new(Path::new("project"), &[]),
+                    &source,
+                )
+                .expect("Graph should be properly created");
+
+                let paths = graph.paths().collect::<Vec<_>>();
+                assert!(paths.contains(&Path::new("project/std/sdf.wgsl")));
+                assert!(!paths.iter().any(|path| path.starts_with(VIRTUAL_ROOT)));
+                assert!(graph.depends_on(Path::new("<std>/noise/perlin.wgsl")));
+                assert!(graph.depends_on(Path::new("<std>/utils/fmod.wgsl")));
+                assert!(!graph.depends_on(Path::new("<std>/sdf.wgsl")));
+            },
+            || {},
+        ));
+    }
+}
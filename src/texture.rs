@@ -11,14 +11,11 @@ impl Texture {
     pub fn from_file(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        asset_path: &str,
+        path: &std::path::Path,
     ) -> anyhow::Result<Self> {
-        let path = std::path::Path::new(env!("OUT_DIR"))
-            .join("assets")
-            .join(asset_path);
         println!("Loading texture from {:?}", path);
-        let data = std::fs::read(path).unwrap();
-        Self::from_bytes(device, queue, &data, asset_path)
+        let data = std::fs::read(path)?;
+        Self::from_bytes(device, queue, &data, &path.display().to_string())
     }
 
     pub fn from_bytes(
@@ -92,10 +89,14 @@ impl Texture {
 
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
     pub const DIFFUSE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+    /// Format of the textures compute shaders write to, `DIFFUSE_FORMAT` can't be used for storage
+    pub const STORAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 
-    pub fn create_diffuse_texture(
+    /// Creates a texture the size of the surface that can be rendered to with `format`.
+    pub fn create_target(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
         label: &str,
     ) -> Self {
         let size = wgpu::Extent3d {
@@ -110,10 +111,15 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: Self::DIFFUSE_FORMAT,
+            format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::TEXTURE_BINDING
-                | wgpu::TextureUsages::COPY_SRC,
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST
+                | match format {
+                    Self::STORAGE_FORMAT => wgpu::TextureUsages::STORAGE_BINDING,
+                    _ => wgpu::TextureUsages::empty(),
+                },
             view_formats: &[],
         };
         let texture = device.create_texture(&desc);
@@ -140,81 +146,3 @@ impl Texture {
         }
     }
 }
-
-pub struct TextureBind {
-    pub texture: Texture,
-    pub bind_group_layout: wgpu::BindGroupLayout,
-    pub bind_group: wgpu::BindGroup,
-}
-impl TextureBind {
-    pub fn new(
-        device: &wgpu::Device,
-        surface_config: &wgpu::SurfaceConfiguration,
-        label: &str,
-    ) -> Self {
-        let texture = Texture::create_diffuse_texture(device, surface_config, label);
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Texture Bind Group Layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-        });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Diffuse Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
-                },
-            ],
-        });
-
-        Self {
-            texture,
-            bind_group_layout,
-            bind_group,
-        }
-    }
-}
-
-pub struct TexturePair(TextureBind, TextureBind, bool);
-impl TexturePair {
-    pub fn new(device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) -> Self {
-        let mut textures = ["First TexturePair FBO", "Second TexturePair FBO"]
-            .iter()
-            .map(|label| TextureBind::new(device, surface_config, label));
-        TexturePair(textures.next().unwrap(), textures.next().unwrap(), false)
-    }
-
-    pub fn swap(&mut self) {
-        self.2 = !self.2;
-    }
-
-    pub fn get(&self) -> (&TextureBind, &TextureBind) {
-        if self.2 {
-            (&self.0, &self.1)
-        } else {
-            (&self.1, &self.0)
-        }
-    }
-}
@@ -1,55 +1,64 @@
-use std::time::Instant;
+use std::time::Duration;
+
+use crate::clock::SharedClock;
 
 pub struct SimpleTimer {
     target: u128,
-    start: Option<Instant>,
+    clock: SharedClock,
+    start: Option<Duration>,
 }
 
 impl SimpleTimer {
-    pub fn from_ms(target: u128) -> Self {
+    pub fn from_ms(target: u128, clock: SharedClock) -> Self {
         Self {
             target,
+            clock,
             start: None,
         }
     }
 
     pub fn start(&mut self) {
-        self.start = Some(Instant::now());
+        self.start = Some(self.clock.now());
     }
 
     pub fn current(&self) -> Option<u128> {
-        self.start.map(|start| start.elapsed().as_millis())
+        self.start
+            .map(|start| self.clock.now().saturating_sub(start).as_millis())
     }
 
     pub fn remaining(&self) -> Option<u128> {
-        self.start
-            .map(|start| self.target - start.elapsed().as_millis())
+        self.current()
+            .map(|current| self.target.saturating_sub(current))
     }
 
     pub fn is_finished(&self) -> bool {
-        match self.start {
-            Some(instant) if instant.elapsed().as_millis() > self.target => true,
-            _ => false,
-        }
+        matches!(self.current(), Some(current) if current > self.target)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::test::*;
+    use crate::{clock::ManualClock, test::*};
+
+    use std::rc::Rc;
 
     #[test]
     fn timer_300ms() {
         run_test(Test::new(
             || {},
             || {
-                let mut timer = SimpleTimer::from_ms(300);
+                let clock = Rc::new(ManualClock::default());
+                let mut timer = SimpleTimer::from_ms(300, clock.clone());
+                assert!(!timer.is_finished());
+
                 timer.start();
-                std::thread::sleep(std::time::Duration::from_millis(200));
+                clock.advance(Duration::from_millis(200));
                 assert!(!timer.is_finished());
-                std::thread::sleep(std::time::Duration::from_millis(200));
+                assert_eq!(timer.remaining(), Some(100));
+                clock.advance(Duration::from_millis(200));
                 assert!(timer.is_finished());
+                assert_eq!(timer.remaining(), Some(0));
             },
             || {},
         ));
@@ -1,16 +1,23 @@
-use std::path::PathBuf;
+use crate::clock::SharedClock;
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use {
     notify::{
         event::{AccessKind, AccessMode},
         EventKind, RecursiveMode, Watcher,
     },
-    wgpu::util::DeviceExt,
+    serde::Deserialize,
 };
 
 pub struct FileWatcher {
     pub watcher: notify::RecommendedWatcher,
     pub event_receiver: std::sync::mpsc::Receiver<Vec<PathBuf>>,
+    watched: HashSet<PathBuf>,
 }
 
 impl FileWatcher {
@@ -31,62 +38,272 @@ impl FileWatcher {
         Self {
             watcher,
             event_receiver: rx,
+            watched: HashSet::new(),
         }
     }
 
-    pub fn watch(&mut self, path: &std::path::Path) {
-        self.watcher
+    pub fn watch(&mut self, path: &Path) {
+        if self.watched.contains(path) {
+            return;
+        }
+
+        if let Err(err) = self
+            .watcher
             .watch(path, notify::RecursiveMode::NonRecursive)
-            .expect("Should start watching file");
+        {
+            eprintln!("Failed to start watching '{}': {err}", path.display());
+            return;
+        }
+        self.watched.insert(path.to_path_buf());
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        if !self.watched.remove(path) {
+            return;
+        }
+
+        // The file may have been deleted, in which case the backend already dropped it
+        if let Err(err) = self.watcher.unwatch(path) {
+            eprintln!("Failed to stop watching '{}': {err}", path.display());
+        }
+    }
+
+    /// Makes the watched set match `paths` exactly, starting and stopping watches as needed.
+    pub fn sync(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        let wanted = paths.into_iter().collect::<HashSet<_>>();
+
+        let stale = self
+            .watched
+            .difference(&wanted)
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in stale {
+            self.unwatch(&path);
+        }
+
+        for path in wanted.iter() {
+            self.watch(path);
+        }
+    }
+}
+
+/// Playback settings of the scene time, `globals.time` in shaders.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TimeConfig {
+    /// Time the playback starts at, in seconds
+    pub start: f32,
+    pub paused: bool,
+    /// Playback speed multiplier
+    pub speed: f32,
+    /// Duration of a single step, in seconds
+    pub step: f32,
+    /// Times jumped to with the 1-9 keys, in seconds
+    pub bookmarks: Vec<f32>,
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            start: 0.,
+            paused: false,
+            speed: 1.,
+            step: 1. / 60.,
+            bookmarks: Vec::new(),
+        }
     }
 }
 
+/// Time seen by every pass, following the clock at a given speed unless paused.
 pub struct SceneTime {
-    pub start: std::time::Instant,
-    pub previous_update: std::time::Instant,
+    pub clock: SharedClock,
+    /// Time of the clock at the previous update
+    pub previous_update: Duration,
+
+    /// Scene time at the moment the clock was at `anchor`, in seconds
+    base: f64,
+    anchor: Duration,
+    paused: bool,
+    speed: f64,
+    step: f64,
+    pub bookmarks: Vec<f32>,
 
-    pub buffer: wgpu::Buffer,
-    pub bind_group_layout: wgpu::BindGroupLayout,
-    pub bind_group: wgpu::BindGroup,
     pub deltas_last_second: Vec<f32>,
 }
 
 impl SceneTime {
-    pub fn new(device: &wgpu::Device) -> Self {
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Time Buffer"),
-            contents: bytemuck::cast_slice(&[0.0f32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Time Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Time Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-        });
-
+    pub fn new(clock: SharedClock, config: &TimeConfig) -> Self {
         Self {
-            start: std::time::Instant::now(),
-            previous_update: std::time::Instant::now(),
-            buffer,
-            bind_group_layout,
-            bind_group,
+            previous_update: clock.now(),
+            base: config.start as f64,
+            anchor: clock.now(),
+            paused: config.paused,
+            speed: config.speed as f64,
+            step: config.step as f64,
+            bookmarks: config.bookmarks.clone(),
+            clock,
             deltas_last_second: Vec::new(),
         }
     }
+
+    /// Time elapsed on the clock since the previous update.
+    pub fn since_update(&self) -> Duration {
+        self.clock.now().saturating_sub(self.previous_update)
+    }
+
+    /// Current scene time, in seconds.
+    pub fn seconds(&self) -> f64 {
+        match self.paused {
+            true => self.base,
+            false => {
+                let elapsed = self.clock.now().saturating_sub(self.anchor);
+                self.base + elapsed.as_secs_f64() * self.speed
+            }
+        }
+    }
+
+    /// Restarts the playback from the current scene time, before changing how it moves.
+    fn rebase(&mut self) {
+        self.base = self.seconds();
+        self.anchor = self.clock.now();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.rebase();
+        self.paused = paused;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.rebase();
+        self.speed = speed;
+    }
+
+    /// Jumps to `seconds`, clamped to 0.
+    pub fn seek(&mut self, seconds: f64) {
+        self.rebase();
+        self.base = seconds.max(0.);
+    }
+
+    /// Pauses and moves by `steps` steps, backwards if negative.
+    pub fn step(&mut self, steps: i32) {
+        self.set_paused(true);
+        self.seek(self.base + steps as f64 * self.step);
+    }
+
+    /// Applies the settings that differ between `previous` and `config`, keeping the current
+    /// time unless the start changed.
+    pub fn reconfigure(&mut self, previous: &TimeConfig, config: &TimeConfig) {
+        if config.start != previous.start {
+            self.seek(config.start as f64);
+        }
+        if config.paused != previous.paused {
+            self.set_paused(config.paused);
+        }
+        if config.speed != previous.speed {
+            self.set_speed(config.speed as f64);
+        }
+        self.step = config.step as f64;
+        self.bookmarks = config.bookmarks.clone();
+    }
+}
+
+impl std::fmt::Display for SceneTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "time: {:.3}s", self.seconds())?;
+        if self.paused {
+            write!(f, " (paused)")?;
+        }
+        if self.speed != 1. {
+            write!(f, " x{}", self.speed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Year, month (1-12) and day (1-31) of a number of days since 1970-01-01, see
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        clock::ManualClock,
+        test::{run_test, Test},
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn scene_time() {
+        run_test(Test::new(
+            || {},
+            || {
+                let clock = Rc::new(ManualClock::default());
+                let config = TimeConfig {
+                    start: 1.,
+                    speed: 2.,
+                    step: 0.5,
+                    ..TimeConfig::default()
+                };
+                let mut time = SceneTime::new(clock.clone(), &config);
+                assert_eq!(time.seconds(), 1.);
+
+                clock.advance(Duration::from_secs(1));
+                assert_eq!(time.seconds(), 3.);
+
+                time.set_paused(true);
+                clock.advance(Duration::from_secs(1));
+                assert_eq!(time.seconds(), 3.);
+
+                time.step(-3);
+                assert!(time.is_paused());
+                assert_eq!(time.seconds(), 1.5);
+
+                time.seek(-4.);
+                assert_eq!(time.seconds(), 0.);
+
+                // Speed changes only apply from the current time on
+                time.set_paused(false);
+                clock.advance(Duration::from_secs(1));
+                time.set_speed(0.5);
+                clock.advance(Duration::from_secs(2));
+                assert_eq!(time.seconds(), 3.);
+
+                // Only what changed in the config gets applied
+                let paused = TimeConfig {
+                    paused: true,
+                    speed: 1.,
+                    step: 0.25,
+                    ..config.clone()
+                };
+                time.reconfigure(&config, &paused);
+                clock.advance(Duration::from_secs(1));
+                assert_eq!(time.seconds(), 3.);
+                assert_eq!(time.speed(), 1.);
+                time.step(1);
+                assert_eq!(time.seconds(), 3.25);
+            },
+            || {},
+        ));
+    }
 }
@@ -11,7 +11,7 @@ fn valueNoise2(n: vec2f) -> f32 {
     let d = vec2f(0., 1.);
     let b = floor(n);
     let f = smoothstep(vec2f(0.), vec2f(1.), fract(n));
-    return mix(mix(rand22(b), rand22(b + d.yx), f.x), mix(rand22(b + d.xy), rand22(b + d.yy), f.x), f.y);
+    return mix(mix(rand22_sin(b), rand22_sin(b + d.yx), f.x), mix(rand22_sin(b + d.xy), rand22_sin(b + d.yy), f.x), f.y);
 }
 
 // MIT License. © Stefan Gustavson, Munrocket
@@ -0,0 +1,38 @@
+// One of the noises of `lib/noise`, selected with the `NOISE` define
+//% include <std/utils/gen_triangle_vs>
+//% include "generated/globals"
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+//% if NOISE == 0
+//% include <std/noise/perlin>
+fn noise(p: vec2f) -> f32 {
+    return mix(perlinNoise2(p), perlinNoise3(vec3f(p, globals.time)), .5) * .5 + .5;
+}
+//% else
+//% if NOISE == 1
+//% include <std/noise/simplex>
+fn noise(p: vec2f) -> f32 {
+    return simplexNoise2(p + globals.time) * .5 + .5;
+}
+//% else
+//% if NOISE == 2
+//% include <std/noise/voronoi>
+fn noise(p: vec2f) -> f32 {
+    return voroNoise2(p + globals.time, 1., .5);
+}
+//% else
+//% include <std/noise/value>
+fn noise(p: vec2f) -> f32 {
+    let value = valueNoise(p.x + globals.time) + valueNoise2(p) + valueNoise3(vec3f(p, globals.time));
+    return value / 3.;
+}
+//% endif
+//% endif
+//% endif
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4f(vec3f(noise(in.tex_coords * 8.)), 1.);
+}
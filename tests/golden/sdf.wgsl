@@ -0,0 +1,34 @@
+// Shapes of `lib/sdf` combined around the mouse, drawn with their distance lines
+//% include <std/utils/gen_triangle_vs>
+//% include <std/sdf>
+//% include "generated/mouse_state"
+//% include "generated/globals"
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = in.tex_coords;
+
+    let cursor = disc(uv, globals.cursor / globals.resolution.xy, .15);
+    let box = rectangle(uv, vec2f(.3, .7), vec2f(.2, .1));
+    let ring = subtract(disc(uv, vec2f(.7, .3), .2), disc(uv, vec2f(.7, .3), .1));
+
+    var dist: f32;
+    switch mouse_state_tag(globals.mouse_state) {
+        case Held: {
+            dist = round_intersect(cursor, merge(box, ring), .05);
+        }
+        case Clicked: {
+            dist = round_subtract(merge(box, ring), cursor, .05);
+        }
+        case Idle, default: {
+            dist = round_merge(cursor, merge(box, ring), .05);
+        }
+    }
+
+    let lines = smoothstep(.0, .01, abs(fract(dist * 10. + .5) - .5) / 10.);
+    let color = select(vec3f(.2, .6, 1.), vec3f(1., .4, .2), dist < 0.);
+    return vec4f(lines * color, 1.);
+}